@@ -1,8 +1,266 @@
 //! Abstract syntax tree representation for Ruby programming language.
 
+use regex::Regex;
+use std::ops::RangeInclusive;
+
+/// Ruby version as major and minor number, e.g. `RubyVersion(2, 7)`.
+/// Used to find out the minimum ruby version which supports a syntax.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RubyVersion(pub u8, pub u8);
+
 /// Represent ruby source code as a list of expressions.
 pub struct Root {
+    /// Interpreter directive on the first line, `#!/usr/bin/env ruby`,
+    /// without `#!` prefix.
+    pub(crate) shebang: Option<String>,
+
+    /// Magic comments before the first expression, at most one of each kind.
+    pub(crate) magic_comments: Vec<MagicComment>,
+
     pub(crate) expressions: Vec<Expression>,
+
+    /// Comments which are not attached to any expression,
+    /// e.g. comments after the last expression.
+    pub(crate) comments: Vec<CommentVariants>,
+
+    /// Data section after `__END__`, readable through `DATA` constant.
+    pub(crate) data: Option<String>,
+
+    /// Original source, printed as is while the tree is unchanged.
+    /// Any modification through `Root` drops it, statements which are kept
+    /// as `Expression::Verbatim` are still printed as they were.
+    pub(crate) source: Option<String>,
+}
+
+impl Root {
+    /// Create program from its top level statements,
+    /// without shebang, magic comments or data section.
+    pub fn new(expressions: Vec<Expression>) -> Root {
+        Root {
+            shebang: None,
+            magic_comments: Vec::new(),
+            expressions,
+            comments: Vec::new(),
+            data: None,
+            source: None,
+        }
+    }
+
+    /// Keep the original source for lossless printing, it should be set
+    /// after the other fields since setting them drops the source.
+    /// Lossless printing works at statement level, there is no concrete syntax
+    /// tree of tokens: unchanged tree prints the source as is, modified tree
+    /// reprints statements kept as `Expression::Verbatim` and re-emits the rest,
+    /// so layout inside a modified statement, e.g. spacing, is not kept.
+    pub fn set_source(&mut self, source: String) {
+        self.source = Some(source);
+    }
+
+    /// Tokens of the original source including whitespaces and comments.
+    /// Tokens aren't kept along with the tree, they are lexed from the source,
+    /// so this returns `None` once the source is dropped, i.e. after any
+    /// modification through `Root`, including `attach_comments`.
+    pub fn tokens(&self) -> Option<Vec<crate::lexer::Token>> {
+        self.source
+            .as_deref()
+            .map(|source| crate::lexer::lex(source).collect())
+    }
+
+    pub fn expressions(&self) -> &[Expression] {
+        &self.expressions
+    }
+
+    /// Comments which are not attached to any statement.
+    pub fn comments(&self) -> &[CommentVariants] {
+        &self.comments
+    }
+
+    /// Attach comments to the innermost statement by position.
+    /// `lines` holds the lines of each top level statement in order,
+    /// each comment comes with the line it starts at.
+    /// Comments before a statement become its leading comments,
+    /// a line comment on its last line becomes its trailing comment,
+    /// and comments after the last statement are kept unattached.
+    /// Comments inside a statement go to its nested statements the same way,
+    /// except comments on its first line, e.g. `class Foo # :nodoc:`,
+    /// and after its last nested statement, which become its leading comments.
+    pub fn attach_comments(
+        &mut self,
+        comments: Vec<(usize, CommentVariants)>,
+        lines: &[StatementLines],
+    ) {
+        self.source = None;
+        let expressions = std::mem::take(&mut self.expressions);
+        let (expressions, unattached) = attach_comments(expressions, comments, lines);
+        self.expressions = expressions;
+        self.comments.extend(unattached);
+    }
+
+    /// Top level statements for modification, the original source is dropped
+    /// so unchanged statements are printed from their `Expression::Verbatim`.
+    pub fn expressions_mut(&mut self) -> &mut Vec<Expression> {
+        self.source = None;
+        &mut self.expressions
+    }
+
+    /// Interpreter directive without `#!` prefix, e.g. `/usr/bin/env ruby`.
+    pub fn shebang(&self) -> Option<&str> {
+        self.shebang.as_deref()
+    }
+
+    /// Set or remove interpreter directive, it's given without `#!` prefix.
+    pub fn set_shebang(&mut self, shebang: Option<String>) {
+        self.source = None;
+        self.shebang = shebang;
+    }
+
+    /// Content after `__END__` line, if any.
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    /// Set or remove data section, `__END__` line is emitted before `data`.
+    pub fn set_data(&mut self, data: Option<String>) {
+        self.source = None;
+        self.data = data;
+    }
+
+    /// Magic comments in the order they were added,
+    /// they are emitted in canonical order regardless.
+    pub fn magic_comments(&self) -> &[MagicComment] {
+        &self.magic_comments
+    }
+
+    /// Add magic comment, replacing the existing one of the same kind,
+    /// e.g. `# frozen_string_literal: false` is replaced by
+    /// `# frozen_string_literal: true`.
+    pub fn set_magic_comment(&mut self, comment: MagicComment) {
+        self.source = None;
+        self.remove_magic_comment(&comment);
+        self.magic_comments.push(comment);
+    }
+
+    /// Remove magic comment of the same kind as `comment`, regardless its value.
+    pub fn remove_magic_comment(&mut self, comment: &MagicComment) {
+        self.source = None;
+        self.magic_comments
+            .retain(|mc| std::mem::discriminant(mc) != std::mem::discriminant(comment));
+    }
+
+    /// Value of `# frozen_string_literal:` magic comment, if any.
+    pub fn frozen_string_literal(&self) -> Option<bool> {
+        self.magic_comments.iter().find_map(|mc| match mc {
+            MagicComment::FrozenStringLiteral(frozen) => Some(*frozen),
+            _ => None,
+        })
+    }
+
+    /// Source encoding from `# encoding:` magic comment, if any.
+    pub fn encoding(&self) -> Option<&str> {
+        self.magic_comments.iter().find_map(|mc| match mc {
+            MagicComment::Encoding(encoding) => Some(encoding.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Sorbet strictness level from `# typed:` sigil, if any.
+    pub fn typed(&self) -> Option<&TypedSigil> {
+        self.magic_comments.iter().find_map(|mc| match mc {
+            MagicComment::Typed(sigil) => Some(sigil),
+            _ => None,
+        })
+    }
+}
+
+/// Magic comment, variants are in their canonical order.
+/// Encoding has to be in the first line, or the second line after shebang.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MagicComment {
+    /// `# encoding: utf-8`, also recognized as `coding`.
+    Encoding(String),
+
+    /// `# frozen_string_literal: true`
+    FrozenStringLiteral(bool),
+
+    /// `# warn_indent: true`
+    WarnIndent(bool),
+
+    /// `# shareable_constant_value: literal`, available since ruby 3.0.
+    ShareableConstantValue(ShareableConstantValue),
+
+    /// Sorbet sigil, `# typed: strict`.
+    Typed(TypedSigil),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareableConstantValue {
+    None,
+    Literal,
+    ExperimentalEverything,
+    ExperimentalCopy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypedSigil {
+    Ignore,
+    False,
+    True,
+    Strict,
+    Strong,
+}
+
+impl MagicComment {
+    /// Recognize magic comment from comment text without `#` prefix,
+    /// e.g. ` frozen_string_literal: true` or emacs style ` -*- coding: utf-8 -*-`.
+    /// Names are case insensitive and `-` is the same as `_`, like ruby does.
+    pub fn parse(comment: &str) -> Option<MagicComment> {
+        let comment = comment.trim();
+        match comment.strip_prefix("-*-") {
+            // Emacs style may have several pairs, e.g. `-*- mode: ruby; coding: utf-8 -*-`.
+            Some(emacs) => emacs
+                .strip_suffix("-*-")?
+                .split(';')
+                .find_map(MagicComment::parse_pair),
+            None => MagicComment::parse_pair(comment),
+        }
+    }
+
+    fn parse_pair(pair: &str) -> Option<MagicComment> {
+        let (name, value) = pair.split_once(':')?;
+        let name = name.trim().to_lowercase().replace('-', "_");
+        let value = value.trim();
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            return None;
+        }
+        let flag = || match value.to_lowercase().as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match name.as_str() {
+            "encoding" | "coding" => Some(MagicComment::Encoding(value.to_string())),
+            "frozen_string_literal" => flag().map(MagicComment::FrozenStringLiteral),
+            "warn_indent" => flag().map(MagicComment::WarnIndent),
+            "shareable_constant_value" => match value {
+                "none" => Some(ShareableConstantValue::None),
+                "literal" => Some(ShareableConstantValue::Literal),
+                "experimental_everything" => Some(ShareableConstantValue::ExperimentalEverything),
+                "experimental_copy" => Some(ShareableConstantValue::ExperimentalCopy),
+                _ => None,
+            }
+            .map(MagicComment::ShareableConstantValue),
+            "typed" => match value {
+                "ignore" => Some(TypedSigil::Ignore),
+                "false" => Some(TypedSigil::False),
+                "true" => Some(TypedSigil::True),
+                "strict" => Some(TypedSigil::Strict),
+                "strong" => Some(TypedSigil::Strong),
+                _ => None,
+            }
+            .map(MagicComment::Typed),
+            _ => None,
+        }
+    }
 }
 
 /// Expression in ruby can be splitted into some more specific types.
@@ -18,17 +276,665 @@ pub enum Expression {
     MethodUndefinition(MethodUndefinition),
     Aliasing(AliasingVariants),
     SendMethod(SendMethodVariants),
+    /// Proc or lambda object, e.g. `->(a) { a }`.
+    Proc(ProcExpressionVariants),
     Operation(OperationVariants),
     LogicalOperation(LogicalOperationVariants),
     Branching(BranchingVariants),
     TernaryBranching(TernaryBranching),
     CaseMatching(CaseMatching),
     Loop(LoopVariants),
-    Return(Option<Box<Expression>>),
+    /// `return`, may return multiple values, e.g. `return a, *b`.
+    Return(Vec<ArrayInterpolation>),
+
+    /// `break`, may break with multiple values.
+    Break(Vec<ArrayInterpolation>),
+
+    /// `next`, may skip with multiple values, e.g. `next a, b`.
+    Next(Vec<ArrayInterpolation>),
+    Redo,
     ExceptionHandling(ExceptionHandlingVariants),
-    BEGINBlock(Vec<Expression>),
-    ENDBlock(Vec<Expression>),
+    Retry,
+    BEGINBlock(BEGINBlock),
+    ENDBlock(ENDBlock),
     FlipFlop(FlipFlopVariants),
+
+    /// Expression along with its comments, comments are emitted around
+    /// the expression when it's a statement, e.g. in method body,
+    /// elsewhere the expression is wrapped in parentheses along with them.
+    Commented(Comments, Box<Expression>),
+
+    /// Statement along with its original source, printed as is
+    /// until it's modified through `Expression::make_mut`.
+    Verbatim(Verbatim),
+}
+
+impl Expression {
+    /// Keep original source of a statement for lossless printing,
+    /// see `Verbatim` for what `leading` and `text` cover.
+    pub fn verbatim(self, leading: String, text: String) -> Expression {
+        Expression::Verbatim(Verbatim {
+            leading,
+            text: Some(text),
+            expression: Box::new(self),
+        })
+    }
+
+    /// Expression for modification, its original source is dropped so it's re-emitted,
+    /// while lines before it, e.g. comments and blank lines, are still printed as is.
+    /// Since reaching a nested statement means modifying its ancestors,
+    /// ancestors are re-emitted while untouched siblings are still printed as is.
+    pub fn make_mut(&mut self) -> &mut Expression {
+        match self {
+            Expression::Verbatim(v) => {
+                v.text = None;
+                &mut v.expression
+            }
+            exp => exp,
+        }
+    }
+
+    /// Attach comments to the expression, comments are appended
+    /// if the expression already has comments.
+    pub fn with_comments(self, comments: Comments) -> Expression {
+        match self {
+            Expression::Commented(mut attached, exp) => {
+                attached.leading.extend(comments.leading);
+                if comments.trailing.is_some() {
+                    attached.trailing = comments.trailing;
+                }
+                Expression::Commented(attached, exp)
+            }
+            exp => Expression::Commented(comments, Box::new(exp)),
+        }
+    }
+
+    /// Documentation comment of class, module or method definition,
+    /// e.g. YARD or RDoc, one line per comment line without `#` prefix.
+    /// Returns `None` if the definition has no leading comment.
+    pub fn doc_comment(&self) -> Option<String> {
+        match self {
+            Expression::Commented(comments, exp) if !comments.leading.is_empty() => match **exp {
+                Expression::ClassDefinition(_)
+                | Expression::ModuleDefinition(_)
+                | Expression::MethodDefinition(_) => Some(
+                    comments
+                        .leading
+                        .iter()
+                        .map(|comment| match comment {
+                            CommentVariants::Line(text) => {
+                                format!("{}\n", text.strip_prefix(' ').unwrap_or(text))
+                            }
+                            CommentVariants::Block(text) => text.clone(),
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Expressions directly nested in this expression, e.g. operands,
+    /// arguments, default values of parameters, and statements of bodies and blocks.
+    pub fn children(&self) -> Vec<&Expression> {
+        let mut children = Vec::new();
+        match self {
+            Expression::Literal(value) => value_children(value, &mut children),
+            Expression::Access(_)
+            | Expression::MethodUndefinition(_)
+            | Expression::Aliasing(_)
+            | Expression::Redo
+            | Expression::Retry => (),
+            Expression::Assignment(assignment) => match assignment {
+                AssignmentVariants::ToLocalVariable(_, exp)
+                | AssignmentVariants::ToInstanceVariable(_, exp)
+                | AssignmentVariants::ToClassVariable(_, exp)
+                | AssignmentVariants::ToGlobalVariable(_, exp)
+                | AssignmentVariants::ToConstant(_, exp)
+                | AssignmentVariants::BinaryOperator(_, _, exp)
+                | AssignmentVariants::LogicalOperator(_, _, exp) => children.push(&**exp),
+                AssignmentVariants::MultipleAssignment(lhs, rhs) => {
+                    for element in &lhs.0 {
+                        left_hand_side_children(element, &mut children);
+                    }
+                    for interpolation in &rhs.0 {
+                        interpolation_children(interpolation, &mut children);
+                    }
+                }
+            },
+            Expression::ClassDefinition(ClassDefinitionVariants::Class(cd)) => {
+                children.extend(cd.parent.as_deref());
+                children.extend(&cd.body.0);
+            }
+            Expression::ClassDefinition(ClassDefinitionVariants::Singleton(scd)) => {
+                children.push(&scd.receiver);
+                children.extend(&scd.body.0);
+            }
+            Expression::ModuleDefinition(md) => children.extend(&md.body.0),
+            Expression::MethodDefinition(MethodDefinitionVariants::Instance(im)) => {
+                parameter_children(&im.args, &mut children);
+                children.extend(&im.body.0);
+            }
+            Expression::MethodDefinition(MethodDefinitionVariants::Singleton(sm)) => {
+                children.push(&sm.receiver);
+                parameter_children(&sm.args, &mut children);
+                children.extend(&sm.body.0);
+            }
+            Expression::MethodDefinition(MethodDefinitionVariants::Endless(em)) => {
+                children.extend(em.receiver.as_deref());
+                parameter_children(&em.args, &mut children);
+                children.push(&em.expression);
+            }
+            Expression::SendMethod(SendMethodVariants::Singleton(sm)) => {
+                send_method_children(sm, &mut children)
+            }
+            Expression::SendMethod(SendMethodVariants::WithReceiver(recv, sm)) => {
+                children.push(recv);
+                send_method_children(sm, &mut children);
+            }
+            Expression::Proc(ProcExpressionVariants::Proc(blk))
+            | Expression::Proc(ProcExpressionVariants::Lambda(blk))
+            | Expression::Proc(ProcExpressionVariants::Stubby(blk)) => {
+                block_children(blk, &mut children)
+            }
+            Expression::Operation(OperationVariants::Paren(stmts)) => children.extend(&stmts.0),
+            Expression::Operation(OperationVariants::BinaryExpression(beo)) => {
+                children.push(&beo.lefthand);
+                children.push(&beo.righthand);
+            }
+            Expression::Operation(OperationVariants::Not(exp)) => children.push(exp),
+            Expression::LogicalOperation(lov) => match lov {
+                LogicalOperationVariants::Equal(left, right)
+                | LogicalOperationVariants::And(left, right)
+                | LogicalOperationVariants::LowerPrecedenceAnd(left, right)
+                | LogicalOperationVariants::Or(left, right)
+                | LogicalOperationVariants::LowerPrecedenceOr(left, right)
+                | LogicalOperationVariants::DoubleAmpersands(left, right)
+                | LogicalOperationVariants::DoublePipes(left, right) => {
+                    children.push(left);
+                    children.push(right);
+                }
+                LogicalOperationVariants::Not(exp) => children.push(exp),
+                LogicalOperationVariants::Match(rem) => {
+                    string_children(&rem.regex.expression, &mut children);
+                    children.push(&rem.expression);
+                }
+            },
+            Expression::Branching(BranchingVariants::If(biv)) => {
+                branching_if_children(biv, &mut children)
+            }
+            Expression::Branching(BranchingVariants::Unless(
+                BranchingUnlessVariants::WithoutElse(bu),
+            )) => {
+                children.push(&bu.condition);
+                children.extend(&bu.iffalse.0);
+            }
+            Expression::Branching(BranchingVariants::Unless(
+                BranchingUnlessVariants::WithElse(tb),
+            ))
+            | Expression::TernaryBranching(tb) => {
+                children.push(&tb.condition);
+                children.extend(&tb.iftrue.0);
+                children.extend(&tb.iffalse.0);
+            }
+            Expression::CaseMatching(cm) => {
+                children.extend(cm.condition.as_deref());
+                for when in &cm.when {
+                    for interpolation in &when.conditions {
+                        interpolation_children(interpolation, &mut children);
+                    }
+                    children.extend(&when.iftrue.0);
+                }
+                if let Some(default) = &cm.default {
+                    children.extend(&default.0);
+                }
+            }
+            Expression::Loop(LoopVariants::PreCondition(lcv))
+            | Expression::Loop(LoopVariants::PostCondition(lcv)) => {
+                let (LoopConditionVariants::While(ls) | LoopConditionVariants::Until(ls)) = lcv;
+                children.push(&ls.condition);
+                children.extend(&ls.body.0);
+            }
+            Expression::Loop(LoopVariants::ForIn(fl)) => {
+                left_hand_side_children(&fl.assignee, &mut children);
+                array_expression_children(&fl.iterator, &mut children);
+                children.extend(&fl.body.0);
+            }
+            Expression::Return(values) | Expression::Break(values) | Expression::Next(values) => {
+                for interpolation in values {
+                    interpolation_children(interpolation, &mut children);
+                }
+            }
+            Expression::ExceptionHandling(ExceptionHandlingVariants::InlineRescue(exp, rescue)) => {
+                children.push(exp);
+                children.push(rescue);
+            }
+            Expression::ExceptionHandling(ExceptionHandlingVariants::Begin(eh))
+            | Expression::ExceptionHandling(ExceptionHandlingVariants::Implicit(eh)) => {
+                children.extend(&eh.body.0);
+                for rescue in &eh.rescues {
+                    for interpolation in &rescue.exceptions {
+                        interpolation_children(interpolation, &mut children);
+                    }
+                    children.extend(&rescue.body.0);
+                }
+                if let Some(otherwise) = &eh.otherwise {
+                    children.extend(&otherwise.0);
+                }
+                if let Some(ensure) = &eh.ensure {
+                    children.extend(&ensure.0);
+                }
+            }
+            Expression::BEGINBlock(BEGINBlock(stmts)) | Expression::ENDBlock(ENDBlock(stmts)) => {
+                children.extend(&stmts.0)
+            }
+            Expression::FlipFlop(FlipFlopVariants::Inclusive(ff))
+            | Expression::FlipFlop(FlipFlopVariants::Exclusive(ff)) => {
+                children.push(&ff.flip);
+                children.push(&ff.flop);
+                children.extend(&ff.body.0);
+            }
+            Expression::Commented(_, exp) => children.push(exp),
+            Expression::Verbatim(v) => children.push(&v.expression),
+        }
+        children
+    }
+
+    /// Whether this expression or any expression nested in it satisfies `predicate`.
+    pub fn any(&self, predicate: &impl Fn(&Expression) -> bool) -> bool {
+        predicate(self) || self.children().into_iter().any(|exp| exp.any(predicate))
+    }
+
+    /// Bodies directly nested in this statement in order, e.g. `if` branch
+    /// followed by `else` branch, or body of a block passed to a method call.
+    /// Like `make_mut`, the original source of the statement is dropped.
+    pub(crate) fn bodies_mut(&mut self) -> Vec<&mut Body> {
+        let mut bodies = Vec::new();
+        match self {
+            Expression::ClassDefinition(ClassDefinitionVariants::Class(cd)) => {
+                bodies.push(&mut cd.body)
+            }
+            Expression::ClassDefinition(ClassDefinitionVariants::Singleton(scd)) => {
+                bodies.push(&mut scd.body)
+            }
+            Expression::ModuleDefinition(md) => bodies.push(&mut md.body),
+            Expression::MethodDefinition(MethodDefinitionVariants::Instance(im)) => {
+                bodies.push(&mut im.body)
+            }
+            Expression::MethodDefinition(MethodDefinitionVariants::Singleton(sm)) => {
+                bodies.push(&mut sm.body)
+            }
+            Expression::SendMethod(SendMethodVariants::Singleton(sm))
+            | Expression::SendMethod(SendMethodVariants::WithReceiver(_, sm)) => {
+                if let Some(BlockArgument::BeginBlock(blk)) = &mut sm.args.1 {
+                    bodies.push(&mut blk.body);
+                }
+            }
+            Expression::Proc(ProcExpressionVariants::Proc(blk))
+            | Expression::Proc(ProcExpressionVariants::Lambda(blk))
+            | Expression::Proc(ProcExpressionVariants::Stubby(blk)) => bodies.push(&mut blk.body),
+            Expression::Branching(BranchingVariants::If(biv)) => {
+                branching_if_bodies(biv, &mut bodies)
+            }
+            Expression::Branching(BranchingVariants::Unless(
+                BranchingUnlessVariants::WithoutElse(bu),
+            )) => bodies.push(&mut bu.iffalse),
+            Expression::Branching(BranchingVariants::Unless(
+                BranchingUnlessVariants::WithElse(tb),
+            )) => {
+                bodies.push(&mut tb.iftrue);
+                bodies.push(&mut tb.iffalse);
+            }
+            Expression::CaseMatching(cm) => {
+                bodies.extend(cm.when.iter_mut().map(|when| &mut when.iftrue));
+                bodies.extend(cm.default.as_mut());
+            }
+            Expression::Loop(LoopVariants::PreCondition(lcv))
+            | Expression::Loop(LoopVariants::PostCondition(lcv)) => {
+                let (LoopConditionVariants::While(ls) | LoopConditionVariants::Until(ls)) = lcv;
+                bodies.push(&mut ls.body);
+            }
+            Expression::Loop(LoopVariants::ForIn(fl)) => bodies.push(&mut fl.body),
+            Expression::ExceptionHandling(ExceptionHandlingVariants::Begin(eh))
+            | Expression::ExceptionHandling(ExceptionHandlingVariants::Implicit(eh)) => {
+                bodies.push(&mut eh.body);
+                bodies.extend(eh.rescues.iter_mut().map(|rescue| &mut rescue.body));
+                bodies.extend(eh.otherwise.as_mut());
+                bodies.extend(eh.ensure.as_mut());
+            }
+            Expression::BEGINBlock(BEGINBlock(stmts)) | Expression::ENDBlock(ENDBlock(stmts)) => {
+                bodies.push(stmts)
+            }
+            Expression::Commented(_, exp) => return exp.bodies_mut(),
+            Expression::Verbatim(_) => return self.make_mut().bodies_mut(),
+            _ => (),
+        }
+        bodies
+    }
+}
+
+fn value_children<'e>(value: &'e ValueVariants, children: &mut Vec<&'e Expression>) {
+    match value {
+        ValueVariants::String(s, _)
+        | ValueVariants::Symbol(s, _)
+        | ValueVariants::ExecuteString(s, _) => string_children(s, children),
+        ValueVariants::HereDocument(HereDocumentVariants::Plain(hd))
+        | ValueVariants::HereDocument(HereDocumentVariants::Dash(hd))
+        | ValueVariants::HereDocument(HereDocumentVariants::Squiggly(hd)) => {
+            string_children(&hd.document, children)
+        }
+        ValueVariants::RegularExpression(re) => string_children(&re.expression, children),
+        ValueVariants::Array(al) => array_literal_children(al, children),
+        ValueVariants::Hash(hl) => hash_literal_children(hl, children),
+        ValueVariants::Singleton(_)
+        | ValueVariants::Integer(_)
+        | ValueVariants::Float(_)
+        | ValueVariants::Complex(_)
+        | ValueVariants::Rational(_)
+        | ValueVariants::Range(_) => (),
+    }
+}
+
+fn string_children<'e>(s: &'e StringLiteral, children: &mut Vec<&'e Expression>) {
+    if let StringLiteral::WithInterpolation(parts) = s {
+        children.extend(parts);
+    }
+}
+
+fn array_literal_children<'e>(al: &'e ArrayLiteral, children: &mut Vec<&'e Expression>) {
+    match al {
+        ArrayLiteral::Plain(elements) => children.extend(elements),
+        ArrayLiteral::Splat(aexp) => array_expression_children(aexp, children),
+        ArrayLiteral::WithInterpolation(elements) => {
+            for interpolation in elements {
+                interpolation_children(interpolation, children);
+            }
+        }
+        ArrayLiteral::Words(words, _) | ArrayLiteral::Symbols(words, _) => {
+            for word in words {
+                string_children(word, children);
+            }
+        }
+    }
+}
+
+fn array_expression_children<'e>(aexp: &'e ArrayExpression, children: &mut Vec<&'e Expression>) {
+    if let ArrayExpression::Literal(al) = aexp {
+        array_literal_children(al, children);
+    }
+}
+
+fn interpolation_children<'e>(
+    interpolation: &'e ArrayInterpolation,
+    children: &mut Vec<&'e Expression>,
+) {
+    match interpolation {
+        ArrayInterpolation::Expression(exp) => children.push(exp),
+        ArrayInterpolation::Splat(aexp) => array_expression_children(aexp, children),
+    }
+}
+
+fn hash_literal_children<'e>(hl: &'e HashLiteral, children: &mut Vec<&'e Expression>) {
+    match hl {
+        HashLiteral::Plain(elements) => {
+            for element in elements {
+                hash_element_children(element, children);
+            }
+        }
+        HashLiteral::Splat(hexp) => hash_expression_children(hexp, children),
+        HashLiteral::WithInterpolation(elements) => {
+            for interpolation in elements {
+                match interpolation {
+                    HashInterpolation::Element(element) => hash_element_children(element, children),
+                    HashInterpolation::Splat(hexp) => hash_expression_children(hexp, children),
+                }
+            }
+        }
+    }
+}
+
+fn hash_element_children<'e>(element: &'e HashElement, children: &mut Vec<&'e Expression>) {
+    match element {
+        HashElement::Pair(pair) => {
+            children.push(&pair.key);
+            children.push(&pair.value);
+        }
+        HashElement::WithLabel(labeled) => {
+            string_children(&labeled.key, children);
+            children.push(&labeled.value);
+        }
+        HashElement::Shorthand(_) => (),
+    }
+}
+
+fn hash_expression_children<'e>(hexp: &'e HashExpression, children: &mut Vec<&'e Expression>) {
+    if let HashExpression::Literal(hl) = hexp {
+        hash_literal_children(hl, children);
+    }
+}
+
+fn left_hand_side_children<'e>(
+    element: &'e MultipleLeftHandSideElement,
+    children: &mut Vec<&'e Expression>,
+) {
+    match element {
+        MultipleLeftHandSideElement::IndexAccess(aiv) => {
+            for interpolation in &aiv.index {
+                interpolation_children(interpolation, children);
+            }
+        }
+        MultipleLeftHandSideElement::Nested(mlhs) => {
+            for element in &mlhs.0 {
+                left_hand_side_children(element, children);
+            }
+        }
+        MultipleLeftHandSideElement::Splat(Some(element)) => {
+            left_hand_side_children(element, children)
+        }
+        MultipleLeftHandSideElement::PlainAccess(_)
+        | MultipleLeftHandSideElement::AttributeAccess(_)
+        | MultipleLeftHandSideElement::Splat(None) => (),
+    }
+}
+
+fn parameter_children<'e>(args: &'e FormalArgument, children: &mut Vec<&'e Expression>) {
+    for param in &args.0 {
+        if let Parameter::Optional(_, exp) | Parameter::KeywordOptional(_, exp) = param {
+            children.push(exp);
+        }
+    }
+}
+
+fn send_method_children<'e>(sm: &'e SendMethod, children: &mut Vec<&'e Expression>) {
+    argument_children(&sm.args, children);
+    match &sm.args.1 {
+        Some(BlockArgument::BeginBlock(blk))
+        | Some(BlockArgument::Pass(ProcAsArgumentVariants::Expression(
+            ProcExpressionVariants::Proc(blk)
+            | ProcExpressionVariants::Lambda(blk)
+            | ProcExpressionVariants::Stubby(blk),
+        ))) => block_children(blk, children),
+        Some(BlockArgument::Pass(ProcAsArgumentVariants::ToProc(exp))) => children.push(exp),
+        Some(BlockArgument::Pass(ProcAsArgumentVariants::Variable(_)))
+        | Some(BlockArgument::Pass(ProcAsArgumentVariants::Symbol(_)))
+        | Some(BlockArgument::AnonymousPass)
+        | None => (),
+    }
+}
+
+/// Expressions nested in arguments, without the block argument.
+fn argument_children<'e>(args: &'e SendMethodArgument, children: &mut Vec<&'e Expression>) {
+    for arg in &args.0 {
+        match arg {
+            ArgumentVariants::Expression(exp) => children.push(exp),
+            ArgumentVariants::Splat(aexp) => array_expression_children(aexp, children),
+            ArgumentVariants::Keyword(element) => hash_element_children(element, children),
+            ArgumentVariants::KeywordSplat(hexp) => hash_expression_children(hexp, children),
+            ArgumentVariants::AnonymousSplat
+            | ArgumentVariants::AnonymousKeywordSplat
+            | ArgumentVariants::ForwardArguments => (),
+        }
+    }
+}
+
+fn block_children<'e>(blk: &'e Block, children: &mut Vec<&'e Expression>) {
+    if let BlockParameters::Explicit(args, ..) = &blk.params {
+        parameter_children(args, children);
+    }
+    children.extend(&blk.body.0);
+}
+
+fn branching_if_bodies<'e>(biv: &'e mut BranchingIfVariants, bodies: &mut Vec<&'e mut Body>) {
+    match biv {
+        BranchingIfVariants::WithoutElse(bi) => bodies.push(&mut bi.iftrue),
+        BranchingIfVariants::WithElse(tb) => {
+            bodies.push(&mut tb.iftrue);
+            bodies.push(&mut tb.iffalse);
+        }
+        BranchingIfVariants::WithElsif(web) => {
+            bodies.push(&mut web.iftrue);
+            branching_if_bodies(&mut web.elsif, bodies);
+        }
+    }
+}
+
+fn branching_if_children<'e>(biv: &'e BranchingIfVariants, children: &mut Vec<&'e Expression>) {
+    match biv {
+        BranchingIfVariants::WithoutElse(bi) => {
+            children.push(&bi.condition);
+            children.extend(&bi.iftrue.0);
+        }
+        BranchingIfVariants::WithElse(tb) => {
+            children.push(&tb.condition);
+            children.extend(&tb.iftrue.0);
+            children.extend(&tb.iffalse.0);
+        }
+        BranchingIfVariants::WithElsif(web) => {
+            children.push(&web.condition);
+            children.extend(&web.iftrue.0);
+            branching_if_children(&web.elsif, children);
+        }
+    }
+}
+
+/// Attach comments to `statements` and their nested statements,
+/// see `Root::attach_comments`, returns comments after the last statement.
+fn attach_comments(
+    statements: Vec<Expression>,
+    comments: Vec<(usize, CommentVariants)>,
+    lines: &[StatementLines],
+) -> (Vec<Expression>, Vec<CommentVariants>) {
+    let lines = &lines[..lines.len().min(statements.len())];
+    let mut attached = lines
+        .iter()
+        .map(|_| Comments::new(Vec::new(), None))
+        .collect::<Vec<Comments>>();
+    let mut nested = lines.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    let mut unattached = Vec::new();
+    for (line, comment) in comments {
+        if let CommentVariants::Line(text) = &comment {
+            // The last statement ending on the line, e.g. `b` in `a; b # text`.
+            if let Some(i) = lines.iter().rposition(|l| *l.lines.end() == line) {
+                if attached[i].trailing.is_none() {
+                    attached[i].trailing = Some(text.clone());
+                    continue;
+                }
+            }
+        }
+        match lines.iter().position(|l| line <= *l.lines.end()) {
+            Some(i) if line > *lines[i].lines.start() && !lines[i].body.is_empty() => {
+                nested[i].push((line, comment))
+            }
+            Some(i) => attached[i].leading.push(comment),
+            None => unattached.push(comment),
+        }
+    }
+    let mut attached = attached.into_iter().zip(nested).zip(lines);
+    let statements = statements
+        .into_iter()
+        .map(|mut exp| match attached.next() {
+            Some(((mut comments, nested), lines)) => {
+                if !nested.is_empty() {
+                    let mut bodies = exp.bodies_mut();
+                    let lengths = bodies.iter().map(|b| b.0.len()).collect::<Vec<usize>>();
+                    let statements = bodies
+                        .iter_mut()
+                        .flat_map(|b| std::mem::take(&mut b.0))
+                        .collect();
+                    let (statements, unattached) = attach_comments(statements, nested, &lines.body);
+                    let mut statements = statements.into_iter();
+                    for (body, length) in bodies.into_iter().zip(lengths) {
+                        body.0 = statements.by_ref().take(length).collect();
+                    }
+                    comments.leading.extend(unattached);
+                }
+                if comments.is_empty() {
+                    exp
+                } else {
+                    exp.with_comments(comments)
+                }
+            }
+            None => exp,
+        })
+        .collect();
+    (statements, unattached)
+}
+
+/// Lines of a statement for attaching comments, the first and the last line
+/// along with lines of its nested statements, see `Expression::bodies_mut`.
+pub struct StatementLines {
+    pub(crate) lines: RangeInclusive<usize>,
+    pub(crate) body: Vec<StatementLines>,
+}
+
+impl StatementLines {
+    /// `body` holds nested statements of all bodies in order,
+    /// e.g. statements of `if` branch followed by statements of `else` branch.
+    pub fn new(lines: RangeInclusive<usize>, body: Vec<StatementLines>) -> StatementLines {
+        StatementLines { lines, body }
+    }
+}
+
+/// Original source of a statement, covering whole lines
+/// so it can be printed as is at the same indentation.
+pub struct Verbatim {
+    /// Lines before the statement, e.g. blank lines and comments,
+    /// each line ends with newline.
+    pub(crate) leading: String,
+
+    /// Lines of the statement including indentation, without the last newline,
+    /// `None` once the statement is modified through `Expression::make_mut`.
+    pub(crate) text: Option<String>,
+    pub(crate) expression: Box<Expression>,
+}
+
+/// Comments attached to an expression.
+pub struct Comments {
+    /// Comments above the expression, e.g. documentation of a method.
+    pub(crate) leading: Vec<CommentVariants>,
+
+    /// Comment at the end of the expression's last line, `foo # text`,
+    /// text is without `#` prefix.
+    pub(crate) trailing: Option<String>,
+}
+
+impl Comments {
+    pub fn new(leading: Vec<CommentVariants>, trailing: Option<String>) -> Comments {
+        Comments { leading, trailing }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_none()
+    }
+}
+
+pub enum CommentVariants {
+    /// Line comment, `# text`, text is without `#` prefix.
+    Line(String),
+
+    /// Embedded document, lines between `=begin` and `=end`.
+    Block(String),
 }
 
 /// Represent variants of literal value.
@@ -38,10 +944,10 @@ pub enum ValueVariants {
     Float(FloatLiteral),
     Complex(ComplexLiteral),
     Rational(RationalLiteral),
-    String(StringLiteral),
-    Symbol(StringLiteral),
+    String(StringLiteral, LiteralForm),
+    Symbol(StringLiteral, LiteralForm),
     HereDocument(HereDocumentVariants),
-    ExecuteString(StringLiteral),
+    ExecuteString(StringLiteral, LiteralForm),
     RegularExpression(RegularExpression),
     Array(ArrayLiteral),
     Hash(HashLiteral),
@@ -55,6 +961,17 @@ pub enum SingletonVariants {
     Nil,
 }
 
+impl SingletonVariants {
+    /// Keyword spelling the value, also listed in `lexer::KEYWORDS`.
+    pub const fn keyword(&self) -> &'static str {
+        match self {
+            SingletonVariants::True => "true",
+            SingletonVariants::False => "false",
+            SingletonVariants::Nil => "nil",
+        }
+    }
+}
+
 /// Literal representation for signed integer.
 pub struct IntegerLiteral(pub(crate) i64);
 
@@ -71,9 +988,208 @@ pub struct ComplexLiteral(pub(crate) f64);
 /// Quotes will be determined by each variants.
 pub enum StringLiteral {
     Static(String),
+
+    /// String with bytes which aren't printable or valid in its encoding,
+    /// e.g. `"\xFF"` or packed binary data. Bytes are emitted with escapes.
+    Bytes(ByteString),
     WithInterpolation(Vec<Expression>),
 }
 
+/// Raw bytes of a string literal along with its encoding.
+pub struct ByteString {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) encoding: StringEncoding,
+}
+
+/// Encoding of string literal, it follows the source encoding
+/// from `# encoding:` magic comment, except literal with `\u` escape
+/// which is always UTF-8. Emitter keeps encoding other than UTF-8
+/// with a method call, e.g. `"\xFF".b`, regardless the source encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StringEncoding {
+    Utf8,
+
+    /// `ASCII-8BIT`, also known as `BINARY`.
+    Binary,
+    UsAscii,
+
+    /// Other encoding by its name, e.g. `Shift_JIS`.
+    Other(String),
+}
+
+impl ByteString {
+    pub fn new(bytes: Vec<u8>, encoding: StringEncoding) -> ByteString {
+        ByteString { bytes, encoding }
+    }
+
+    /// Decode escape sequences of double quoted string content, e.g. `\n`, `\x7F`,
+    /// `\101`, `\u00e9`, `\u{1F600 20}`, `\cx`, `\C-x` or `\M-\C-x`.
+    /// Literal with `\u` escape is UTF-8 regardless the given encoding.
+    /// Returns `None` on invalid escape sequence, e.g. `\x` without hex digit.
+    pub fn unescape(source: &str, encoding: StringEncoding) -> Option<ByteString> {
+        let mut bytes = Vec::with_capacity(source.len());
+        let mut encoding = encoding;
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                push_char(&mut bytes, c);
+                continue;
+            }
+            match chars.next()? {
+                'u' => {
+                    encoding = StringEncoding::Utf8;
+                    if chars.peek() == Some(&'{') {
+                        chars.next();
+                        loop {
+                            while chars.peek() == Some(&' ') {
+                                chars.next();
+                            }
+                            if chars.peek() == Some(&'}') {
+                                chars.next();
+                                break;
+                            }
+                            let code = hex_digits(&mut chars, 6)?;
+                            push_char(&mut bytes, char::from_u32(code)?);
+                        }
+                    } else {
+                        let code = (0..4)
+                            .map(|_| chars.next()?.to_digit(16))
+                            .try_fold(0, |code, digit| Some(code * 16 + digit?))?;
+                        push_char(&mut bytes, char::from_u32(code)?);
+                    }
+                }
+                '\n' => (),
+                // Unknown escape keeps the character, e.g. `\é` is `é`.
+                c if !c.is_ascii() => push_char(&mut bytes, c),
+                c => bytes.push(escaped_byte(c, &mut chars)?),
+            }
+        }
+        Some(ByteString { bytes, encoding })
+    }
+}
+
+fn push_char(bytes: &mut Vec<u8>, c: char) {
+    let mut buffer = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+}
+
+/// Parse at most `max` hex digits, returns `None` if there's none.
+fn hex_digits(chars: &mut std::iter::Peekable<std::str::Chars>, max: usize) -> Option<u32> {
+    let mut value = None;
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(16)) {
+            Some(digit) => {
+                value = Some(value.unwrap_or(0) * 16 + digit);
+                chars.next();
+            }
+            None => break,
+        }
+    }
+    value
+}
+
+/// Single byte value of an escape sequence after the backslash,
+/// meta and control escapes may be combined, e.g. `\M-\C-x`.
+fn escaped_byte(c: char, chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u8> {
+    let byte = match c {
+        'n' => b'\n',
+        't' => b'\t',
+        's' => b' ',
+        'r' => b'\r',
+        'a' => 0x07,
+        'b' => 0x08,
+        'e' => 0x1b,
+        'f' => 0x0c,
+        'v' => 0x0b,
+        '0'..='7' => {
+            let mut value = c.to_digit(8)?;
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        value = value * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            (value & 0xff) as u8
+        }
+        'x' => hex_digits(chars, 2)? as u8,
+        'c' => control(chars)?,
+        'C' => {
+            if chars.next()? != '-' {
+                return None;
+            }
+            control(chars)?
+        }
+        'M' => {
+            if chars.next()? != '-' {
+                return None;
+            }
+            match chars.next()? {
+                '\\' => escaped_byte(chars.next()?, chars)? | 0x80,
+                c if c.is_ascii() => c as u8 | 0x80,
+                _ => return None,
+            }
+        }
+        c if c.is_ascii() => c as u8,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+/// Control character of the following character, `\c?` is delete.
+fn control(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u8> {
+    let byte = match chars.next()? {
+        '\\' => escaped_byte(chars.next()?, chars)?,
+        c if c.is_ascii() => c as u8,
+        _ => return None,
+    };
+    Some(if byte == b'?' { 0x7f } else { byte & 0x9f })
+}
+
+/// How a string, symbol, command or regular expression literal is written,
+/// so formatted output keeps the original delimiters.
+/// Forms which doesn't apply to the literal, e.g. non-interpolated form
+/// for a literal with interpolation, fall back to the default form.
+pub enum LiteralForm {
+    /// `"str"`, `:sym` or `:"sym"`, `` `cmd` `` and `/regex/`.
+    Default,
+
+    /// `'str'` or `:'sym'`.
+    SingleQuoted,
+
+    /// Percent literal, e.g. `%q{str}`, `%Q(str)`, `%s[sym]`, `%x(cmd)` or `%r{regex}`.
+    Percent(PercentDelimiter),
+
+    /// Character literal, e.g. `?a`, only for a single character string.
+    Character,
+}
+
+/// Delimiter of a percent literal.
+pub struct PercentDelimiter {
+    /// Opening delimiter, e.g. `{`, its closing pair is `}`.
+    /// Non bracket delimiter is closed by the same character, e.g. `%q|str|`.
+    pub(crate) open: char,
+
+    /// Whether the literal is written in its interpolated form,
+    /// e.g. `%Q` instead of `%q`, or `%W` instead of `%w`.
+    /// Symbols are never interpolated and commands and regular
+    /// expressions are always interpolated, so it's ignored for them.
+    pub(crate) interpolated: bool,
+}
+
+impl PercentDelimiter {
+    /// Returns `None` if `open` is not an ASCII punctuation,
+    /// since ruby doesn't accept alphanumeric or whitespace delimiters.
+    pub fn new(open: char, interpolated: bool) -> Option<PercentDelimiter> {
+        if !open.is_ascii_punctuation() {
+            return None;
+        }
+        Some(PercentDelimiter { open, interpolated })
+    }
+}
+
 /// Here document representations, there are 2 kinds
 /// of here document in ruby, plain heredoc, and squiggly heredoc
 pub enum HereDocumentVariants {
@@ -87,28 +1203,67 @@ pub struct HereDocument {
     pub(crate) document: StringLiteral,
 }
 
-/// Literal representation for regular expression
+/// Literal representation for regular expression,
+/// in default form it's emitted as `%r{}` if its source contains `/`.
 pub struct RegularExpression {
     pub(crate) expression: StringLiteral,
     pub(crate) options: Vec<RegularExpressionFlag>,
+    pub(crate) form: LiteralForm,
 }
 
 /// Represent regular expression flag,
-/// E is fixed-encofing
+/// E is EUC-JP encoding
 /// I is case-insensitive
 /// M is multi-line
-/// N is no-encoding
-/// U is unicode
+/// N is no-encoding (ASCII-8BIT)
+/// O is interpolate once
+/// S is Windows-31J encoding
+/// U is unicode (UTF-8)
 /// X is extended
 pub enum RegularExpressionFlag {
     E,
     I,
     M,
     N,
+    O,
+    S,
     U,
     X,
 }
 
+impl RegularExpressionFlag {
+    /// Whether this flag sets regular expression encoding,
+    /// only one of them can be set.
+    pub fn is_encoding(&self) -> bool {
+        matches!(
+            self,
+            RegularExpressionFlag::E
+                | RegularExpressionFlag::N
+                | RegularExpressionFlag::S
+                | RegularExpressionFlag::U
+        )
+    }
+}
+
+impl RegularExpression {
+    /// Create regular expression literal,
+    /// returns `None` if more than one encoding flag is set.
+    pub fn new(
+        expression: StringLiteral,
+        options: Vec<RegularExpressionFlag>,
+        form: LiteralForm,
+    ) -> Option<RegularExpression> {
+        if options.iter().filter(|fl| fl.is_encoding()).count() > 1 {
+            return None;
+        }
+        Some(RegularExpression {
+            expression,
+            options,
+            form,
+        })
+    }
+}
+
 /// Literal representation for array.
 pub enum ArrayLiteral {
     /// Plain array is a list of expression.
@@ -122,6 +1277,12 @@ pub enum ArrayLiteral {
     /// as a literal which contains splat in its declaration,
     /// in this case, we categorized this array literal as interpolated.
     WithInterpolation(Vec<ArrayInterpolation>),
+
+    /// Array of words, e.g. `%w[a b]` or `%W[#{a} b]`.
+    Words(Vec<StringLiteral>, PercentDelimiter),
+
+    /// Array of symbols, e.g. `%i[x y]` or `%I[x#{y} z]`.
+    Symbols(Vec<StringLiteral>, PercentDelimiter),
 }
 
 /// Specific expression that returns array.
@@ -132,6 +1293,15 @@ pub enum ArrayExpression {
     Access(AccessVariants),
 }
 
+impl ArrayExpression {
+    /// Expressions directly nested in the array literal, see `Expression::children`.
+    pub fn children(&self) -> Vec<&Expression> {
+        let mut children = Vec::new();
+        array_expression_children(self, &mut children);
+        children
+    }
+}
+
 /// Represent each element in interpolated array.
 pub enum ArrayInterpolation {
     Expression(Expression),
@@ -159,10 +1329,27 @@ pub enum HashLiteral {
 /// a labeled value:
 ///     key: :value
 ///
+/// or
+/// a shorthand, which takes its value from the variable or method with the same name:
+///     key:
+///
 /// Each of this need to be represented separately.
 pub enum HashElement {
     Pair(PairElement),
     WithLabel(LabeledElement),
+
+    /// Shorthand element, e.g. `{x:, y:}` or `foo(x:)`, available since ruby 3.1.
+    Shorthand(Variable),
+}
+
+impl HashElement {
+    /// Minimum ruby version which supports this element syntax.
+    pub fn minimum_version(&self) -> Option<RubyVersion> {
+        match self {
+            HashElement::Shorthand(_) => Some(RubyVersion(3, 1)),
+            _ => None,
+        }
+    }
 }
 
 pub struct PairElement {
@@ -170,6 +1357,8 @@ pub struct PairElement {
     pub(crate) value: Expression,
 }
 
+/// Labeled element, its key is quoted when it's not a valid bare label,
+/// e.g. `"content-type": "json"`.
 pub struct LabeledElement {
     pub(crate) key: StringLiteral,
     pub(crate) value: Expression,
@@ -184,7 +1373,7 @@ pub enum HashExpression {
 
 /// Represent each possible value for interpolated hash element
 pub enum HashInterpolation {
-    Element(HashElement),
+    Element(Box<HashElement>),
     Splat(HashExpression),
 }
 
@@ -209,20 +1398,181 @@ pub enum AccessVariants {
 pub struct Variable(pub(crate) String);
 
 /// global variable is prefixed by dollar '$'
+/// Validated global variable can be built with `GlobalVariable::parse`.
 pub enum GlobalVariable {
+    /// Named global variable, e.g. `$foo`, `$LOAD_PATH` or `$DEBUG`.
     Plain(Variable),
+
+    /// Numbered match reference, e.g. `$1`, parser's `nth_ref`.
     NthReference(IntegerLiteral),
+
+    /// Match back reference, e.g. `$&`, parser's `back_ref`.
+    BackReference(BackReference),
+
+    /// Command line option switch, e.g. `$-w`.
+    Switch(char),
+
+    /// `$:`, load path.
     Colon,
+
+    /// `$*`, command line arguments.
     Splat,
+
+    /// `$?`, status of the last executed child process.
     QuestionMark,
+
+    /// `$$`, current process id.
     Dollar,
+
+    /// `$~`, last match data.
     Tilde,
+
+    /// `$!`, last raised exception.
+    Bang,
+
+    /// `$@`, backtrace of the last raised exception.
+    AtSymbol,
+
+    /// `$;`, default field separator for `String#split`.
+    Semicolon,
+
+    /// `$,`, default output field separator.
+    Comma,
+
+    /// `$/`, input record separator.
+    Slash,
+
+    /// `$\`, output record separator.
+    Backslash,
+
+    /// `$.`, line number of the last read input.
+    Dot,
+
+    /// `$_`, last read input line.
+    Underscore,
+
+    /// `$<`, `ARGF`.
+    LessThan,
+
+    /// `$>`, default output.
+    GreaterThan,
+
+    /// `$0`, program name.
+    Zero,
+
+    /// `$"`, loaded features.
+    DoubleQuote,
+}
+
+/// Match back references, e.g. `$&`.
+pub enum BackReference {
+    /// `$&`, last matched string.
     Ampersand,
+
+    /// `$+`, last matched group.
     Plus,
+
+    /// `` $` ``, string before last match.
     Backtick,
+
+    /// `$'`, string after last match.
     Aposthrope,
-    Bang,
-    AtSymbol,
+}
+
+impl GlobalVariable {
+    /// Parse global variable as written including its `$` prefix,
+    /// returns `None` if it's not a valid global variable.
+    pub fn parse(name: &str) -> Option<GlobalVariable> {
+        let name = name.strip_prefix('$')?;
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if let Some(special) = GlobalVariable::special(c) {
+                return Some(special);
+            }
+        }
+        if let Some(switch) = name.strip_prefix('-') {
+            let mut chars = switch.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() || c == '_' => {
+                    Some(GlobalVariable::Switch(c))
+                }
+                _ => None,
+            };
+        }
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            return match name.parse::<i64>() {
+                Ok(n) if n > 0 && !name.starts_with('0') => {
+                    Some(GlobalVariable::NthReference(IntegerLiteral(n)))
+                }
+                _ => None,
+            };
+        }
+        if !IDENTIFIER.is_match(name) {
+            return None;
+        }
+        Some(GlobalVariable::Plain(Variable(name.to_string())))
+    }
+
+    /// Special global variable spelled with a single punctuation
+    /// (or `0`) after `$`, e.g. `!` for `$!`.
+    pub(crate) fn special(c: char) -> Option<GlobalVariable> {
+        Some(match c {
+            ':' => GlobalVariable::Colon,
+            '*' => GlobalVariable::Splat,
+            '?' => GlobalVariable::QuestionMark,
+            '$' => GlobalVariable::Dollar,
+            '~' => GlobalVariable::Tilde,
+            '!' => GlobalVariable::Bang,
+            '@' => GlobalVariable::AtSymbol,
+            ';' => GlobalVariable::Semicolon,
+            ',' => GlobalVariable::Comma,
+            '/' => GlobalVariable::Slash,
+            '\\' => GlobalVariable::Backslash,
+            '.' => GlobalVariable::Dot,
+            '_' => GlobalVariable::Underscore,
+            '<' => GlobalVariable::LessThan,
+            '>' => GlobalVariable::GreaterThan,
+            '0' => GlobalVariable::Zero,
+            '"' => GlobalVariable::DoubleQuote,
+            '&' => GlobalVariable::BackReference(BackReference::Ampersand),
+            '+' => GlobalVariable::BackReference(BackReference::Plus),
+            '`' => GlobalVariable::BackReference(BackReference::Backtick),
+            '\'' => GlobalVariable::BackReference(BackReference::Aposthrope),
+            _ => return None,
+        })
+    }
+
+    /// Spelling after `$`, the inverse of `GlobalVariable::parse`,
+    /// e.g. `!` for `$!` or `-w` for `$-w`.
+    pub(crate) fn spelling(&self) -> String {
+        let special = match self {
+            GlobalVariable::Plain(v) => return v.0.clone(),
+            GlobalVariable::NthReference(i) => return i.0.to_string(),
+            GlobalVariable::Switch(c) => return format!("-{}", c),
+            GlobalVariable::Colon => ':',
+            GlobalVariable::Splat => '*',
+            GlobalVariable::QuestionMark => '?',
+            GlobalVariable::Dollar => '$',
+            GlobalVariable::Tilde => '~',
+            GlobalVariable::Bang => '!',
+            GlobalVariable::AtSymbol => '@',
+            GlobalVariable::Semicolon => ';',
+            GlobalVariable::Comma => ',',
+            GlobalVariable::Slash => '/',
+            GlobalVariable::Backslash => '\\',
+            GlobalVariable::Dot => '.',
+            GlobalVariable::Underscore => '_',
+            GlobalVariable::LessThan => '<',
+            GlobalVariable::GreaterThan => '>',
+            GlobalVariable::Zero => '0',
+            GlobalVariable::DoubleQuote => '"',
+            GlobalVariable::BackReference(BackReference::Ampersand) => '&',
+            GlobalVariable::BackReference(BackReference::Plus) => '+',
+            GlobalVariable::BackReference(BackReference::Backtick) => '`',
+            GlobalVariable::BackReference(BackReference::Aposthrope) => '\'',
+        };
+        special.to_string()
+    }
 }
 
 /// Variants for constants.
@@ -251,10 +1601,27 @@ pub enum ConstantVariants {
     Encoding,
 }
 
+impl ConstantVariants {
+    /// Keyword spelling of special constant, e.g. `__FILE__`,
+    /// also listed in `lexer::KEYWORDS`. `None` for named constants.
+    pub const fn keyword(&self) -> Option<&'static str> {
+        match self {
+            ConstantVariants::File => Some("__FILE__"),
+            ConstantVariants::Line => Some("__LINE__"),
+            ConstantVariants::Encoding => Some("__ENCODING__"),
+            ConstantVariants::TopLevel(_)
+            | ConstantVariants::Scoped(_)
+            | ConstantVariants::Unscoped(_) => None,
+        }
+    }
+}
+
 /// Constant name representation.
 pub struct Constant(pub(crate) String);
 
 /// Variants of assignment expression.
+/// Assignment to an attribute or an index is a setter call, e.g. `a.b = 1` is
+/// `MethodName::Setter` and `a[0] = 1` is `MethodName::IndexSetter` sent to `a`.
 pub enum AssignmentVariants {
     /// Assign to local variable.
     ToLocalVariable(Variable, Box<Expression>),
@@ -271,9 +1638,6 @@ pub enum AssignmentVariants {
     /// Assign to constant.
     ToConstant(ConstantVariants, Box<Expression>),
 
-    /// Assign to object attribute.
-    ToAttribute(SendMethodAssignmentVariants),
-
     /// Destructuring, multiple left hand assignment.
     MultipleAssignment(MultipleLeftHandSide, MultipleRightHandSide),
 
@@ -292,8 +1656,14 @@ pub enum MultipleLeftHandSideElement {
     /// Attribute access, for assignment to instance attributes.
     AttributeAccess(AccessAttributeVariants),
 
+    /// Index access, e.g. `a[0], a[1] = 1, 2`.
+    IndexAccess(AccessIndexVariants),
+
     /// Multiple left hand side can be nested.
     Nested(MultipleLeftHandSide),
+
+    /// Splat target collecting the rest of values, `*rest` or anonymous `*`.
+    Splat(Option<Box<MultipleLeftHandSideElement>>),
 }
 
 pub struct AccessAttributeVariants {
@@ -301,11 +1671,57 @@ pub struct AccessAttributeVariants {
     pub(crate) attribute: Variable,
 }
 
+pub struct AccessIndexVariants {
+    pub(crate) receiver: AccessVariants,
+    pub(crate) index: Vec<ArrayInterpolation>,
+}
+
 /// Represent multiple left hand side in an assignment.
+/// Validated left hand side can be built with `MultipleLeftHandSide::new`.
 pub struct MultipleLeftHandSide(pub(crate) Vec<MultipleLeftHandSideElement>);
 
-/// Represent right hand side expression in multiple left hand side assignment.
-pub struct MultipleRightHandSide(pub(crate) Box<ArrayInterpolation>);
+impl MultipleLeftHandSide {
+    /// Create multiple left hand side, returns `None` if it's empty,
+    /// has more than one splat in the same level, or splat target is not
+    /// a single assignable target, e.g. `*(a, b)`.
+    pub fn new(elements: Vec<MultipleLeftHandSideElement>) -> Option<MultipleLeftHandSide> {
+        if elements.is_empty() {
+            return None;
+        }
+        let mut splat = false;
+        for elt in &elements {
+            if let MultipleLeftHandSideElement::Splat(target) = elt {
+                if splat {
+                    return None;
+                }
+                splat = true;
+                if let Some(target) = target {
+                    match **target {
+                        MultipleLeftHandSideElement::Nested(_)
+                        | MultipleLeftHandSideElement::Splat(_) => return None,
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Some(MultipleLeftHandSide(elements))
+    }
+}
+
+/// Represent right hand side expression in multiple left hand side assignment,
+/// a single value is destructured, e.g. `a, b = list`,
+/// multiple values are assigned in order, e.g. `a, b = b, a`.
+pub struct MultipleRightHandSide(pub(crate) Vec<ArrayInterpolation>);
+
+impl MultipleRightHandSide {
+    /// Returns `None` if there is no value.
+    pub fn new(values: Vec<ArrayInterpolation>) -> Option<MultipleRightHandSide> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(MultipleRightHandSide(values))
+    }
+}
 
 /// Assignment operator for binary operation
 /// e.g. `And` is &=
@@ -351,6 +1767,37 @@ pub enum BinaryOperator {
     RightShift,
 }
 
+impl BinaryOperator {
+    pub const ALL: [BinaryOperator; 10] = [
+        BinaryOperator::Add,
+        BinaryOperator::Sub,
+        BinaryOperator::Or,
+        BinaryOperator::Xor,
+        BinaryOperator::And,
+        BinaryOperator::Multiply,
+        BinaryOperator::Divide,
+        BinaryOperator::Mod,
+        BinaryOperator::LeftShift,
+        BinaryOperator::RightShift,
+    ];
+
+    /// Operator spelling without `=` of the assignment form, e.g. `<<`.
+    pub const fn spelling(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Or => "|",
+            BinaryOperator::Xor => "^",
+            BinaryOperator::And => "&",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::LeftShift => "<<",
+            BinaryOperator::RightShift => ">>",
+        }
+    }
+}
+
 /// Assignment operator for logical operation
 /// e.g.
 ///     `And` is &&=
@@ -360,6 +1807,18 @@ pub enum LogicalOperator {
     And,
 }
 
+impl LogicalOperator {
+    pub const ALL: [LogicalOperator; 2] = [LogicalOperator::Or, LogicalOperator::And];
+
+    /// Operator spelling without `=` of the assignment form, e.g. `||`.
+    pub const fn spelling(&self) -> &'static str {
+        match self {
+            LogicalOperator::Or => "||",
+            LogicalOperator::And => "&&",
+        }
+    }
+}
+
 /// Variants for class definition.
 pub enum ClassDefinitionVariants {
     /// Plain class definition
@@ -378,19 +1837,23 @@ pub struct ClassDefinition {
 
     /// Class may inherits another class,
     /// which make the other class its parent.
-    pub(crate) parent: Option<ConstantVariants>,
+    /// Superclass can be any expression, e.g. `Struct.new(:x, :y)`
+    /// or `ActiveRecord::Migration[7.1]`.
+    pub(crate) parent: Option<Box<Expression>>,
 
     /// Class definition may contains method declarations and
-    /// whatnot, list them as expressions.
-    pub(crate) expressions: Vec<Expression>,
+    /// whatnot, list them as statements.
+    pub(crate) body: Body,
 }
 
 /// Singleton class definition
-/// i.e. `class << self`
+/// i.e. `class << self` or `class << some_object`
 /// This class definition doesn't have any name,
-/// so it only represented by its list of expressions.
+/// so it only represented by its receiver and list of expressions.
 pub struct SingletonClassDefinition {
-    pub(crate) expressions: Vec<Expression>,
+    /// Object whose singleton class is opened.
+    pub(crate) receiver: Box<Expression>,
+    pub(crate) body: Body,
 }
 
 /// Represent module definition, it's similar to class
@@ -400,86 +1863,515 @@ pub struct ModuleDefinition {
     pub(crate) name: ConstantVariants,
 
     /// Similar as class body, module
-    /// definition may have list of expressions.
-    pub(crate) expressions: Vec<Expression>,
+    /// definition may have list of statements.
+    pub(crate) body: Body,
 }
 
 pub enum MethodDefinitionVariants {
     Instance(InstanceMethod),
     Singleton(SingletonMethod),
+
+    /// Endless method definition, e.g. `def square(x) = x * x`.
+    Endless(EndlessMethod),
 }
 
 pub struct InstanceMethod {
-    pub(crate) name: VariableOrIndex,
+    pub(crate) name: MethodName,
     pub(crate) args: FormalArgument,
-    pub(crate) expressions: Vec<Expression>,
+    pub(crate) body: Body,
 }
 
+/// Method defined on a single object, e.g. `def self.foo`,
+/// `def obj.foo` or `def (expr).foo`.
 pub struct SingletonMethod {
-    pub(crate) name: Variable,
+    /// Object which the method is defined on.
+    pub(crate) receiver: Box<Expression>,
+    pub(crate) name: MethodName,
     pub(crate) args: FormalArgument,
-    pub(crate) expressions: Vec<Expression>,
+    pub(crate) body: Body,
 }
 
-pub enum VariableOrIndex {
-    Variable(Variable),
+/// Endless method definition, its body is a single expression.
+/// Available since ruby 3.0.
+pub struct EndlessMethod {
+    /// Object which the method is defined on, if it's a singleton method.
+    pub(crate) receiver: Option<Box<Expression>>,
+    pub(crate) name: MethodName,
+    pub(crate) args: FormalArgument,
+    pub(crate) expression: Box<Expression>,
+}
+
+impl EndlessMethod {
+    /// Create endless method definition,
+    /// returns `None` for setter methods since ruby doesn't allow them to be endless.
+    pub fn new(
+        receiver: Option<Box<Expression>>,
+        name: MethodName,
+        args: FormalArgument,
+        expression: Box<Expression>,
+    ) -> Option<EndlessMethod> {
+        match name {
+            MethodName::Setter(_) | MethodName::IndexSetter => None,
+            _ => Some(EndlessMethod {
+                receiver,
+                name,
+                args,
+                expression,
+            }),
+        }
+    }
+}
+
+impl MethodDefinitionVariants {
+    /// Minimum ruby version which supports this definition syntax.
+    pub fn minimum_version(&self) -> Option<RubyVersion> {
+        match self {
+            MethodDefinitionVariants::Instance(im) => im.args.minimum_version(),
+            MethodDefinitionVariants::Singleton(sm) => sm.args.minimum_version(),
+            MethodDefinitionVariants::Endless(em) => {
+                em.args.minimum_version().max(Some(RubyVersion(3, 0)))
+            }
+        }
+    }
+}
+
+/// Name of a method as written in its definition, aliasing or undefinition.
+/// Validated names can be built with `MethodName::parse`.
+pub enum MethodName {
+    /// Plain identifier, e.g. `foo`.
+    Plain(Variable),
+
+    /// Predicate method, e.g. `valid?`.
+    Predicate(Variable),
+
+    /// Bang method, e.g. `save!`.
+    Bang(Variable),
+
+    /// Attribute setter, e.g. `name=`.
+    Setter(Variable),
+
+    /// Operator method, e.g. `==`, `+@` or `<=>`.
+    Operator(OperatorMethod),
+
+    /// Index reader: `[]`.
     Index,
+
+    /// Index setter: `[]=`.
+    IndexSetter,
 }
 
-pub enum AliasingVariants {
-    Method(StringLiteral, StringLiteral),
-    GlobalVariable(GlobalVariable),
+/// Operators which can be defined as a method.
+pub enum OperatorMethod {
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+    /// `+@`
+    UnaryPlus,
+    /// `-@`
+    UnaryMinus,
+    /// `*`
+    Multiply,
+    /// `**`
+    Power,
+    /// `/`
+    Divide,
+    /// `%`
+    Modulo,
+    /// `==`
+    Equal,
+    /// `===`
+    CaseEqual,
+    /// `!=`
+    NotEqual,
+    /// `=~`
+    Match,
+    /// `!~`
+    NotMatch,
+    /// `!`
+    Not,
+    /// `~`
+    Tilde,
+    /// `<=>`
+    Compare,
+    /// `<`
+    Lesser,
+    /// `<=`
+    LesserOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `<<`
+    LeftShift,
+    /// `>>`
+    RightShift,
+    /// `&`
+    And,
+    /// `|`
+    Or,
+    /// `^`
+    Xor,
+    /// `` ` ``
+    Backtick,
 }
 
-pub struct FormalArgument(
-    pub(crate) Vec<PlainArgumentVariants>,
-    pub(crate) Option<SplatsAndBlockArgumentVariants<Variable>>,
-);
+lazy_static! {
+    static ref IDENTIFIER: Regex = Regex::new(r"^[_\p{L}][_\p{L}\p{N}]*$").unwrap();
+}
+
+impl MethodName {
+    /// Parse method name as written after `def`,
+    /// returns `None` if it's not a valid ruby method name.
+    pub fn parse(name: &str) -> Option<MethodName> {
+        if let Some(op) = OperatorMethod::parse(name) {
+            return Some(MethodName::Operator(op));
+        }
+        match name {
+            "[]" => return Some(MethodName::Index),
+            "[]=" => return Some(MethodName::IndexSetter),
+            _ => (),
+        }
+        let (ident, wrap): (&str, fn(Variable) -> MethodName) = match name.chars().last()? {
+            '?' => (&name[..name.len() - 1], MethodName::Predicate),
+            '!' => (&name[..name.len() - 1], MethodName::Bang),
+            '=' => (&name[..name.len() - 1], MethodName::Setter),
+            _ => (name, MethodName::Plain),
+        };
+        if !IDENTIFIER.is_match(ident) {
+            return None;
+        }
+        Some(wrap(Variable(ident.to_string())))
+    }
+}
+
+impl OperatorMethod {
+    pub const ALL: [OperatorMethod; 26] = [
+        OperatorMethod::Plus,
+        OperatorMethod::Minus,
+        OperatorMethod::UnaryPlus,
+        OperatorMethod::UnaryMinus,
+        OperatorMethod::Multiply,
+        OperatorMethod::Power,
+        OperatorMethod::Divide,
+        OperatorMethod::Modulo,
+        OperatorMethod::Equal,
+        OperatorMethod::CaseEqual,
+        OperatorMethod::NotEqual,
+        OperatorMethod::Match,
+        OperatorMethod::NotMatch,
+        OperatorMethod::Not,
+        OperatorMethod::Tilde,
+        OperatorMethod::Compare,
+        OperatorMethod::Lesser,
+        OperatorMethod::LesserOrEqual,
+        OperatorMethod::Greater,
+        OperatorMethod::GreaterOrEqual,
+        OperatorMethod::LeftShift,
+        OperatorMethod::RightShift,
+        OperatorMethod::And,
+        OperatorMethod::Or,
+        OperatorMethod::Xor,
+        OperatorMethod::Backtick,
+    ];
+
+    /// Parse operator method name, returns `None` if it's not a definable operator.
+    pub fn parse(name: &str) -> Option<OperatorMethod> {
+        if name == "~@" {
+            return Some(OperatorMethod::Tilde);
+        }
+        IntoIterator::into_iter(OperatorMethod::ALL).find(|op| op.spelling() == name)
+    }
+
+    /// Method name spelling, e.g. `<=>` or `-@`.
+    pub const fn spelling(&self) -> &'static str {
+        match self {
+            OperatorMethod::Plus => "+",
+            OperatorMethod::Minus => "-",
+            OperatorMethod::UnaryPlus => "+@",
+            OperatorMethod::UnaryMinus => "-@",
+            OperatorMethod::Multiply => "*",
+            OperatorMethod::Power => "**",
+            OperatorMethod::Divide => "/",
+            OperatorMethod::Modulo => "%",
+            OperatorMethod::Equal => "==",
+            OperatorMethod::CaseEqual => "===",
+            OperatorMethod::NotEqual => "!=",
+            OperatorMethod::Match => "=~",
+            OperatorMethod::NotMatch => "!~",
+            OperatorMethod::Not => "!",
+            OperatorMethod::Tilde => "~",
+            OperatorMethod::Compare => "<=>",
+            OperatorMethod::Lesser => "<",
+            OperatorMethod::LesserOrEqual => "<=",
+            OperatorMethod::Greater => ">",
+            OperatorMethod::GreaterOrEqual => ">=",
+            OperatorMethod::LeftShift => "<<",
+            OperatorMethod::RightShift => ">>",
+            OperatorMethod::And => "&",
+            OperatorMethod::Or => "|",
+            OperatorMethod::Xor => "^",
+            OperatorMethod::Backtick => "`",
+        }
+    }
+
+    /// Whether the operator can be called in binary form, e.g. `a + b`.
+    /// Unary operators and `` ` `` are called with no operand on the right.
+    pub fn is_binary(&self) -> bool {
+        !matches!(
+            self,
+            OperatorMethod::UnaryPlus
+                | OperatorMethod::UnaryMinus
+                | OperatorMethod::Not
+                | OperatorMethod::Tilde
+                | OperatorMethod::Backtick
+        )
+    }
+}
+
+/// Variants of `alias`, a method alias, or a global variable alias,
+/// e.g. `alias $new $old`.
+pub enum AliasingVariants {
+    Method(AliasingMethod),
+    GlobalVariable(AliasingVariable),
+}
 
-pub struct DecomposedArgument(
-    pub(crate) Vec<DecomposedArgumentVariants>,
-    pub(crate) Option<SplatsAndBlockArgumentVariants<Variable>>,
-);
+/// Parameter list of a method, block or lambda, modelled after parser's `args` node.
+/// Parameters must be in ruby's order, see `FormalArgument::new`.
+pub struct FormalArgument(pub(crate) Vec<Parameter>);
 
-pub enum PlainArgumentVariants {
+/// Each parameter in a parameter list.
+pub enum Parameter {
+    /// Required parameter, `a`, parser's `arg`. It's a post parameter
+    /// when it comes after optional or rest parameter, e.g. `def f(*rest, last)`.
     Required(Variable),
-    KeywordRequired(Variable),
-    KeywordOptional(Variable, Expression),
-    Optional(Variable, Expression),
+
+    /// Destructured required parameter, e.g. `(a, (b, *c))`, parser's `mlhs`.
     Decomposition(DecomposedArgument),
-}
 
-pub enum SplatsAndBlockArgumentVariants<VarArg> {
-    Splat(VarArg),
-    UnnamedSplat,
-    KeyWordSplat(VarArg),
-    UnnamedKeywordSplat,
-    SplatThenKeywordSplat(VarArg, VarArg),
-    Block(VarArg),
-    SplatThenBlock(VarArg, VarArg),
-    SplatThenKeywordSplatThenBlock(VarArg, VarArg, VarArg),
-}
+    /// Optional parameter, `a = 1`, parser's `optarg`.
+    Optional(Variable, Expression),
 
-pub struct ProcArgument(
-    pub(crate) Vec<ProcArgumentVariants>,
-    pub(crate) Option<SplatsAndBlockArgumentVariants<MultipleLeftHandSideElement>>,
-);
+    /// Rest parameter, `*rest` or anonymous `*`, parser's `restarg`.
+    Rest(Option<Variable>),
 
-pub enum ProcArgumentVariants {
-    PlainArgument(PlainArgumentVariants),
-    MultipleLeftHandSide(MultipleLeftHandSideElement),
-}
+    /// Required keyword parameter, `a:`, parser's `kwarg`.
+    KeywordRequired(Variable),
+
+    /// Optional keyword parameter, `a: 1`, parser's `kwoptarg`.
+    KeywordOptional(Variable, Expression),
+
+    /// Keyword rest parameter, `**opts` or anonymous `**`, parser's `kwrestarg`.
+    KeywordRest(Option<Variable>),
+
+    /// No keyword parameter accepted, `**nil`, parser's `kwnilarg`.
+    NoKeywords,
+
+    /// Block parameter, `&blk` or anonymous `&`, parser's `blockarg`.
+    /// Anonymous block parameter is available since ruby 3.1.
+    Block(Option<Variable>),
+
+    /// Arguments forwarding, `...`, parser's `forward_arg`.
+    /// Available since ruby 2.7, with leading parameters since ruby 3.0.
+    ForwardArguments,
+}
+
+impl FormalArgument {
+    /// Create parameter list, returns `None` if parameters are not in ruby's order:
+    /// required, optional, rest, post (required), keywords,
+    /// keyword rest or `**nil`, then block. `...` can only be preceded by
+    /// required and optional parameters.
+    pub fn new(params: Vec<Parameter>) -> Option<FormalArgument> {
+        // Position of the latest parameter in ruby's parameter order.
+        let mut position = 0;
+        for param in &params {
+            position = match (param, position) {
+                (Parameter::Required(_), 0) | (Parameter::Decomposition(_), 0) => 0,
+                (Parameter::Required(_), 1..=3) | (Parameter::Decomposition(_), 1..=3) => 3,
+                (Parameter::Optional(..), 0..=1) => 1,
+                (Parameter::Rest(_), 0..=1) => 2,
+                (Parameter::KeywordRequired(_), 0..=4)
+                | (Parameter::KeywordOptional(..), 0..=4) => 4,
+                (Parameter::KeywordRest(_), 0..=4) | (Parameter::NoKeywords, 0..=4) => 5,
+                (Parameter::Block(_), 0..=5) => 6,
+                (Parameter::ForwardArguments, 0..=1) => 7,
+                _ => return None,
+            };
+        }
+        Some(FormalArgument(params))
+    }
+
+    /// Minimum ruby version which supports this parameter list.
+    pub fn minimum_version(&self) -> Option<RubyVersion> {
+        self.0.iter().fold(None, |version, param| {
+            version.max(match param {
+                // Leading arguments before `...` are allowed since ruby 3.0.
+                Parameter::ForwardArguments if self.0.len() > 1 => Some(RubyVersion(3, 0)),
+                Parameter::ForwardArguments => Some(RubyVersion(2, 7)),
+                Parameter::Block(None) => Some(RubyVersion(3, 1)),
+                _ => None,
+            })
+        })
+    }
+}
+
+/// Destructured parameter, e.g. `(a, (b, c), *d)`.
+pub struct DecomposedArgument(pub(crate) Vec<DecomposedArgumentVariants>);
 
 pub enum DecomposedArgumentVariants {
     Plain(Variable),
     Nested(DecomposedArgument),
-}
-
-pub struct MethodUndefinition(pub(crate) Vec<StringLiteral>);
 
+    /// Splat in destructuring, `*rest` or anonymous `*`.
+    Splat(Option<Variable>),
+}
+
+/// Parameters of a block, proc or lambda.
+pub enum BlockParameters {
+    /// Block without parameter list, e.g. `{ foo }` or `-> { foo }`.
+    Implicit,
+
+    /// Explicit parameter list along with block-local variables,
+    /// e.g. `{ |a, b; tmp| }`, empty pipes `{ || }` is an explicit
+    /// parameter list without any parameter. The flag keeps a trailing comma,
+    /// `{ |a,| }` takes the first element of an array argument,
+    /// see `BlockParameters::explicit`.
+    Explicit(FormalArgument, Vec<Variable>, bool),
+
+    /// Numbered parameters, e.g. `{ _1 + _2 }`, holds the highest
+    /// parameter number used in the block. Available since ruby 2.7.
+    Numbered(NumberedParameter),
+
+    /// Implicit `it` parameter, e.g. `{ it * 2 }`. Available since ruby 3.4.
+    It,
+}
+
+/// Highest numbered parameter used in a block, between 1 and 9.
+pub struct NumberedParameter(pub(crate) u8);
+
+impl NumberedParameter {
+    /// Returns `None` if number is not between 1 and 9.
+    pub fn new(number: u8) -> Option<NumberedParameter> {
+        if (1..=9).contains(&number) {
+            return Some(NumberedParameter(number));
+        }
+        None
+    }
+
+    /// Highest parameter number, e.g. 2 for a block using `_1` and `_2`.
+    pub fn number(&self) -> u8 {
+        self.0
+    }
+}
+
+impl BlockParameters {
+    /// Create explicit parameter list, returns `None` if it forwards arguments
+    /// with `...`, which is only allowed in method definitions, or there is
+    /// a trailing comma which doesn't follow a required or destructured parameter.
+    pub fn explicit(
+        args: FormalArgument,
+        locals: Vec<Variable>,
+        trailing_comma: bool,
+    ) -> Option<BlockParameters> {
+        if args
+            .0
+            .iter()
+            .any(|p| matches!(p, Parameter::ForwardArguments))
+        {
+            return None;
+        }
+        if trailing_comma
+            && !matches!(
+                args.0.last(),
+                Some(Parameter::Required(_)) | Some(Parameter::Decomposition(_))
+            )
+        {
+            return None;
+        }
+        Some(BlockParameters::Explicit(args, locals, trailing_comma))
+    }
+
+    /// Minimum ruby version which supports this parameter style.
+    pub fn minimum_version(&self) -> Option<RubyVersion> {
+        match self {
+            BlockParameters::Numbered(_) => Some(RubyVersion(2, 7)),
+            BlockParameters::It => Some(RubyVersion(3, 4)),
+            _ => None,
+        }
+    }
+}
+
+/// Method undefinition, e.g. `undef foo, bar=`.
+pub struct MethodUndefinition(pub(crate) Vec<MethodName>);
+
+impl MethodUndefinition {
+    /// Create method undefinition, returns `None` if there is no method name.
+    pub fn new(names: Vec<MethodName>) -> Option<MethodUndefinition> {
+        if names.is_empty() {
+            return None;
+        }
+        Some(MethodUndefinition(names))
+    }
+}
+
+/// Method alias, e.g. `alias new_name old_name`.
 pub struct AliasingMethod {
-    pub(crate) oldname: StringLiteral,
-    pub(crate) newname: StringLiteral,
+    pub(crate) oldname: MethodName,
+    pub(crate) newname: MethodName,
+    pub(crate) style: AliasStyle,
+}
+
+/// How method names are written in `alias`.
+pub enum AliasStyle {
+    /// `alias new_name old_name`
+    Bareword,
+
+    /// `alias :new_name :old_name`
+    Symbol,
+}
+
+impl AliasingMethod {
+    /// Recognize `alias_method :new_name, :old_name` call,
+    /// returns `None` if it's not an `alias_method` call with two static names.
+    /// Note that `alias_method` is a method call, converting it to `alias`
+    /// is only equivalent when it's called on the current class.
+    pub fn from_alias_method(send: &SendMethodVariants) -> Option<AliasingMethod> {
+        let method = match send {
+            SendMethodVariants::Singleton(method) => method,
+            SendMethodVariants::WithReceiver(receiver, method) => match **receiver {
+                Expression::Access(AccessVariants::_Self) => method,
+                _ => return None,
+            },
+        };
+        match (&method.name, &method.args) {
+            (MethodName::Plain(Variable(name)), SendMethodArgument(args, None))
+                if name == "alias_method" =>
+            {
+                match args.as_slice() {
+                    [ArgumentVariants::Expression(newname), ArgumentVariants::Expression(oldname)] => {
+                        Some(AliasingMethod {
+                            oldname: static_method_name(oldname)?,
+                            newname: static_method_name(newname)?,
+                            style: AliasStyle::Symbol,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Method name from a static symbol or string literal, e.g. `:foo` or `"foo"`.
+fn static_method_name(exp: &Expression) -> Option<MethodName> {
+    match exp {
+        Expression::Literal(ValueVariants::Symbol(StringLiteral::Static(name), _))
+        | Expression::Literal(ValueVariants::String(StringLiteral::Static(name), _)) => {
+            MethodName::parse(name)
+        }
+        _ => None,
+    }
 }
 
 pub struct AliasingVariable {
@@ -487,29 +2379,26 @@ pub struct AliasingVariable {
     pub(crate) newname: GlobalVariable,
 }
 
+impl AliasingVariable {
+    /// Create global variable alias, returns `None` if either of them
+    /// is a numbered reference (`$1`), since ruby can't alias them.
+    pub fn new(newname: GlobalVariable, oldname: GlobalVariable) -> Option<AliasingVariable> {
+        match (&newname, &oldname) {
+            (GlobalVariable::NthReference(_), _) | (_, GlobalVariable::NthReference(_)) => None,
+            _ => Some(AliasingVariable { oldname, newname }),
+        }
+    }
+}
+
 pub enum SendMethodVariants {
     Singleton(SendMethod),
     WithReceiver(Box<Expression>, SendMethod),
 }
 
-pub enum SendMethodAssignmentVariants {
-    Plain(SendMethodAssignment),
-    WithIndex(SendMethodAssignmentWithIndex),
-}
-
-pub struct SendMethodAssignment {
-    pub(crate) receiver: Box<Expression>,
-    pub(crate) method: SendMethod,
-}
-
-pub struct SendMethodAssignmentWithIndex {
-    pub(crate) receiver: Box<Expression>,
-    pub(crate) index: Box<Expression>,
-    pub(crate) method: SendMethod,
-}
-
+/// Method invocation, operator and index methods
+/// are emitted with their syntactic sugar, e.g. `a[1]`.
 pub struct SendMethod {
-    pub(crate) name: Variable,
+    pub(crate) name: MethodName,
     pub(crate) args: SendMethodArgument,
 }
 
@@ -519,30 +2408,234 @@ pub struct SendMethodArgument(
 );
 
 pub enum ArgumentVariants {
+    /// Positional argument, a hash literal passed as an expression
+    /// keeps its braces, e.g. `foo({ a: 1 })`.
     Expression(Expression),
     Splat(ArrayExpression),
+
+    /// Element of brace-less trailing hash, e.g. `foo(a, key: 1, "k" => v, x:)`.
     Keyword(HashElement),
+
+    /// Double splat in brace-less trailing hash, e.g. `foo(**opts)`.
     KeywordSplat(HashExpression),
+
+    /// Forward anonymous splat parameter: `foo(*)`, available since ruby 3.2.
+    AnonymousSplat,
+
+    /// Forward anonymous keyword splat parameter: `foo(**)`, available since ruby 3.2.
+    AnonymousKeywordSplat,
+
+    /// Forward all arguments: `foo(...)`, available since ruby 2.7.
+    /// It must be the last argument, see `SendMethodArgument::new`.
+    ForwardArguments,
+}
+
+impl SendMethodArgument {
+    /// Create argument list, `params` are parameters of the enclosing method,
+    /// `None` outside of method definition. Returns `None` if a positional
+    /// argument follows keyword arguments, `...` is not the last argument
+    /// or comes along with a block, or an anonymous argument is forwarded
+    /// without the matching anonymous parameter, e.g. `foo(*)` requires `*`,
+    /// `foo(**)` requires `**`, `foo(&)` requires `&` and `foo(...)` requires `...`.
+    pub fn new(
+        args: Vec<ArgumentVariants>,
+        block: Option<BlockArgument>,
+        params: Option<&FormalArgument>,
+    ) -> Option<SendMethodArgument> {
+        let declares =
+            |matching: fn(&Parameter) -> bool| params.is_some_and(|p| p.0.iter().any(matching));
+        let mut keywords = false;
+        for (i, arg) in args.iter().enumerate() {
+            let valid = match arg {
+                ArgumentVariants::Expression(_) | ArgumentVariants::Splat(_) => !keywords,
+                ArgumentVariants::AnonymousSplat => {
+                    !keywords && declares(|p| matches!(p, Parameter::Rest(None)))
+                }
+                ArgumentVariants::Keyword(_) | ArgumentVariants::KeywordSplat(_) => {
+                    keywords = true;
+                    true
+                }
+                ArgumentVariants::AnonymousKeywordSplat => {
+                    keywords = true;
+                    declares(|p| matches!(p, Parameter::KeywordRest(None)))
+                }
+                ArgumentVariants::ForwardArguments => {
+                    !keywords
+                        && i + 1 == args.len()
+                        && block.is_none()
+                        && declares(|p| matches!(p, Parameter::ForwardArguments))
+                }
+            };
+            if !valid {
+                return None;
+            }
+        }
+        if matches!(block, Some(BlockArgument::AnonymousPass))
+            && !declares(|p| matches!(p, Parameter::Block(None)))
+        {
+            return None;
+        }
+        Some(SendMethodArgument(args, block))
+    }
+
+    /// Minimum ruby version which supports this argument list.
+    pub fn minimum_version(&self) -> Option<RubyVersion> {
+        let args = self.0.iter().fold(None, |version, arg| {
+            version.max(match arg {
+                ArgumentVariants::AnonymousSplat | ArgumentVariants::AnonymousKeywordSplat => {
+                    Some(RubyVersion(3, 2))
+                }
+                ArgumentVariants::ForwardArguments if self.0.len() > 1 => Some(RubyVersion(3, 0)),
+                ArgumentVariants::ForwardArguments => Some(RubyVersion(2, 7)),
+                ArgumentVariants::Keyword(elt) => elt.minimum_version(),
+                _ => None,
+            })
+        });
+        match self.1 {
+            Some(BlockArgument::AnonymousPass) => args.max(Some(RubyVersion(3, 1))),
+            _ => args,
+        }
+    }
 }
 
 pub enum BlockArgument {
+    /// Block pass, e.g. `foo(&blk)` or `foo(&:sym)`.
     Pass(ProcAsArgumentVariants),
-    BeginBlock(ProcArgument, Vec<Expression>),
+
+    /// Block literal, e.g. `foo { |x| x }` or `foo do |x| ... end`.
+    BeginBlock(Block),
+
+    /// Forward anonymous block parameter: `foo(&)`, available since ruby 3.1.
+    AnonymousPass,
+}
+
+/// Block literal along with its parameters.
+pub struct Block {
+    pub(crate) params: BlockParameters,
+    pub(crate) body: Body,
+    pub(crate) style: BlockStyle,
+}
+
+/// Delimiter of a block literal.
+/// Braces and `do ... end` bind differently in ruby, e.g. `puts [1].map do ... end`
+/// passes the block to `puts`, emitter always emits call arguments in parentheses
+/// so both delimiters bind to the nearest call.
+pub enum BlockStyle {
+    /// Braces for a body which fits in one line, `do ... end` otherwise.
+    Auto,
+
+    /// Always braces, unless the body has rescue or ensure clause
+    /// which is only allowed in `do ... end`.
+    Braces,
+
+    /// Always `do ... end`.
+    DoEnd,
 }
 
 pub enum ProcAsArgumentVariants {
+    /// `&blk`
     Variable(Variable),
+
+    /// `&:sym`, e.g. `map(&:to_s)`.
+    Symbol(MethodName),
+
+    /// `&->(x) { x }`
     Expression(ProcExpressionVariants),
+
+    /// Other expression which responds to `to_proc`, e.g. `&method(:puts)`.
+    ToProc(Box<Expression>),
 }
 
 pub enum ProcExpressionVariants {
-    Proc(ProcArgument, Vec<Expression>),
-    Lambda(ProcArgument, Vec<Expression>),
-    Stubby(ProcArgument, Vec<Expression>),
+    /// `proc { |a| }`
+    Proc(Block),
+
+    /// `lambda { |a| }`
+    Lambda(Block),
+
+    /// `->(a) { }`, parameters are placed before the body.
+    Stubby(Block),
+}
+
+impl ProcExpressionVariants {
+    /// Lambda checks its arity and `return` only returns from the lambda itself,
+    /// while proc behaves like a block.
+    pub fn is_lambda(&self) -> bool {
+        !matches!(self, ProcExpressionVariants::Proc(_))
+    }
+
+    /// Convert `lambda { |a| }` to `->(a) { }`, proc is kept as is
+    /// since turning it into lambda changes its semantic.
+    pub fn into_stubby(self) -> ProcExpressionVariants {
+        match self {
+            ProcExpressionVariants::Lambda(blk) => ProcExpressionVariants::Stubby(blk),
+            pexp => pexp,
+        }
+    }
+
+    /// Convert `->(a) { }` to `lambda { |a| }`.
+    pub fn into_lambda(self) -> ProcExpressionVariants {
+        match self {
+            ProcExpressionVariants::Stubby(blk) => ProcExpressionVariants::Lambda(blk),
+            pexp => pexp,
+        }
+    }
+}
+
+impl BlockArgument {
+    /// Convert block literal to a proc passed as block, e.g. `foo { }` to `foo(&proc { })`.
+    /// Proc is used rather than lambda to keep the block's arity and `return` semantic.
+    /// Block which breaks out of it, e.g. `foo { break }`, is kept as is,
+    /// since `break` in a proc created by `proc` raises `LocalJumpError`.
+    pub fn into_pass(self) -> BlockArgument {
+        match self {
+            BlockArgument::BeginBlock(blk) if !blk.body.0.iter().any(breaks) => {
+                BlockArgument::Pass(ProcAsArgumentVariants::Expression(
+                    ProcExpressionVariants::Proc(blk),
+                ))
+            }
+            blkarg => blkarg,
+        }
+    }
+
+    /// Convert proc passed as block to block literal, e.g. `foo(&proc { })` to `foo { }`.
+    /// Lambda is kept as is since it's still a lambda when passed as block.
+    pub fn into_block(self) -> BlockArgument {
+        match self {
+            BlockArgument::Pass(ProcAsArgumentVariants::Expression(
+                ProcExpressionVariants::Proc(blk),
+            )) => BlockArgument::BeginBlock(blk),
+            blkarg => blkarg,
+        }
+    }
+}
+
+/// Whether `break` in the expression breaks out of the enclosing block,
+/// `break` in a nested loop, block, proc or definition belongs to that instead.
+fn breaks(exp: &Expression) -> bool {
+    match exp {
+        Expression::Break(_) => true,
+        Expression::Loop(_)
+        | Expression::Proc(_)
+        | Expression::MethodDefinition(_)
+        | Expression::ClassDefinition(_)
+        | Expression::ModuleDefinition(_) => false,
+        Expression::SendMethod(SendMethodVariants::Singleton(sm)) => {
+            let mut children = Vec::new();
+            argument_children(&sm.args, &mut children);
+            children.into_iter().any(breaks)
+        }
+        Expression::SendMethod(SendMethodVariants::WithReceiver(recv, sm)) => {
+            let mut children = vec![&**recv];
+            argument_children(&sm.args, &mut children);
+            children.into_iter().any(breaks)
+        }
+        _ => exp.children().into_iter().any(breaks),
+    }
 }
 
 pub enum OperationVariants {
-    Paren(Vec<Expression>),
+    Paren(Body),
     BinaryExpression(BinaryExpressionOperation),
     Not(Box<Expression>),
 }
@@ -554,14 +2647,23 @@ pub struct BinaryExpressionOperation {
 }
 
 pub enum LogicalOperationVariants {
+    /// `a == b`.
     Equal(Box<Expression>, Box<Expression>),
+    /// `a && b`, the parser's `and` node; bitwise `&` is a [`BinaryOperator`].
     And(Box<Expression>, Box<Expression>),
+    /// `a and b`.
     LowerPrecedenceAnd(Box<Expression>, Box<Expression>),
+    /// `a || b`, the parser's `or` node; bitwise `|` is a [`BinaryOperator`].
     Or(Box<Expression>, Box<Expression>),
+    /// `a or b`.
     LowerPrecedenceOr(Box<Expression>, Box<Expression>),
+    /// `a && b`, emitted the same as `And`.
     DoubleAmpersands(Box<Expression>, Box<Expression>),
+    /// `a || b`, emitted the same as `Or`.
     DoublePipes(Box<Expression>, Box<Expression>),
+    /// `!a`.
     Not(Box<Expression>),
+    /// `/re/ =~ a`.
     Match(RegularExpressionMatch),
 }
 
@@ -578,12 +2680,13 @@ pub enum BranchingIfVariants {
 
 pub struct BranchingIf {
     pub(crate) condition: Box<Expression>,
-    pub(crate) iftrue: Box<Expression>,
+    pub(crate) iftrue: Body,
+    pub(crate) form: ModifierForm,
 }
 pub struct WithElsifBranching {
-    condition: Box<Expression>,
-    iftrue: Box<Expression>,
-    elsif: Box<BranchingIfVariants>,
+    pub(crate) condition: Box<Expression>,
+    pub(crate) iftrue: Body,
+    pub(crate) elsif: Box<BranchingIfVariants>,
 }
 
 pub enum BranchingUnlessVariants {
@@ -593,28 +2696,66 @@ pub enum BranchingUnlessVariants {
 
 pub struct BranchingUnless {
     pub(crate) condition: Box<Expression>,
-    pub(crate) iffalse: Box<Expression>,
+    pub(crate) iffalse: Body,
+    pub(crate) form: ModifierForm,
+}
+
+/// Whether `if`, `unless`, `while` or `until` is emitted in its modifier form,
+/// e.g. `foo if bar`, or its block form.
+/// Parsed code records the form it was written in so it can be preserved,
+/// set it to `Auto` to let the emitter choose. Modifier form used as a value
+/// is parenthesized, e.g. `x = (foo if bar)`.
+pub enum ModifierForm {
+    /// Modifier form when body is a single statement which fits in one line.
+    Auto,
+
+    /// Always block form.
+    Block,
+
+    /// Modifier form whenever body is a single statement.
+    Modifier,
 }
 
 pub struct TernaryBranching {
     pub(crate) condition: Box<Expression>,
-    pub(crate) iftrue: Box<Expression>,
-    pub(crate) iffalse: Box<Expression>,
+    pub(crate) iftrue: Body,
+    pub(crate) iffalse: Body,
 }
 
+/// Sequence of statements, used wherever ruby allows multiple statements,
+/// e.g. body of `if`, `when` or `else` branch.
+pub struct Body(pub(crate) Vec<Expression>);
+
 pub struct CaseMatching {
     pub(crate) condition: Option<Box<Expression>>,
     pub(crate) when: Vec<WhenDefinitionVariants>,
-    pub(crate) default: Option<Box<Expression>>,
+    pub(crate) default: Option<Body>,
 }
 
+/// A `when` clause, its conditions may include splats,
+/// e.g. `when :a, :b, *OTHERS then`.
 pub struct WhenDefinitionVariants {
-    pub(crate) condition: ArrayInterpolation,
-    pub(crate) iftrue: Box<Expression>,
+    pub(crate) conditions: Vec<ArrayInterpolation>,
+    pub(crate) iftrue: Body,
+    pub(crate) style: WhenStyle,
+}
+
+/// How a `when` clause separates its conditions from its body.
+pub enum WhenStyle {
+    /// `when x then y`, emitted in a single line when the body fits,
+    /// otherwise body is emitted in the following lines after `then`.
+    Then,
+
+    /// Body always starts in a new line after the conditions.
+    Newline,
 }
 
 pub enum LoopVariants {
+    /// `while cond ... end`, or its modifier form `stmt while cond`.
     PreCondition(LoopConditionVariants),
+
+    /// `begin ... end while cond`, body is evaluated before the condition,
+    /// its form is ignored.
     PostCondition(LoopConditionVariants),
     ForIn(ForLoop),
 }
@@ -622,7 +2763,7 @@ pub enum LoopVariants {
 pub struct ForLoop {
     pub(crate) assignee: MultipleLeftHandSideElement,
     pub(crate) iterator: ArrayExpression,
-    pub(crate) expressions: Vec<Expression>,
+    pub(crate) body: Body,
 }
 
 pub enum LoopConditionVariants {
@@ -632,42 +2773,64 @@ pub enum LoopConditionVariants {
 
 pub struct LoopStruct {
     pub(crate) condition: Box<Expression>,
-    pub(crate) expressions: Vec<InLoopExpression>,
-}
-
-pub enum InLoopExpression {
-    Plain(Expression),
-    Break(Option<Expression>),
-    Next(Option<Expression>),
-    Redo,
+    pub(crate) body: Body,
+    pub(crate) form: ModifierForm,
 }
 
 pub enum ExceptionHandlingVariants {
+    /// Modifier form of rescue, e.g. `value rescue nil`.
     InlineRescue(Box<Expression>, Box<Expression>),
-    DefRescue(Vec<Expression>, RescueBodyVariants),
-    BeginRescue(Vec<Expression>, RescueBodyVariants),
-}
-
-pub enum RescueBodyVariants {
-    Rescue(Vec<RescueBody>, Option<RescueEnsureOrElse>),
-    Ensure(Vec<Expression>),
-}
 
+    /// Exception handling in a `begin ... end` block.
+    Begin(ExceptionHandling),
+
+    /// Exception handling directly in a method, class, module or `do ... end`
+    /// block body, without `begin` keyword. It's expected to be the only expression
+    /// in that body, otherwise, or in any other body, e.g. of `if` or `while`,
+    /// it will be emitted as `begin ... end` block.
+    Implicit(ExceptionHandling),
+}
+
+/// Body with its `rescue`, `else` and `ensure` clauses,
+/// all of them may be present at once, e.g. `rescue ... else ... ensure`.
+pub struct ExceptionHandling {
+    pub(crate) body: Body,
+    pub(crate) rescues: Vec<RescueBody>,
+    pub(crate) otherwise: Option<Body>,
+    pub(crate) ensure: Option<Body>,
+}
+
+impl ExceptionHandling {
+    /// Create exception handling body, returns `None` if it has `else` clause
+    /// without any `rescue` clause, or neither `rescue` nor `ensure` clause.
+    pub fn new(
+        body: Body,
+        rescues: Vec<RescueBody>,
+        otherwise: Option<Body>,
+        ensure: Option<Body>,
+    ) -> Option<ExceptionHandling> {
+        if rescues.is_empty() && (otherwise.is_some() || ensure.is_none()) {
+            return None;
+        }
+        Some(ExceptionHandling {
+            body,
+            rescues,
+            otherwise,
+            ensure,
+        })
+    }
+}
+
+/// A `rescue` clause, exceptions may be splatted,
+/// e.g. `rescue Timeout::Error, *NETWORK_ERRORS => e`.
 pub struct RescueBody {
-    pub(crate) exceptions: Vec<ConstantVariants>,
+    pub(crate) exceptions: Vec<ArrayInterpolation>,
     pub(crate) assignment: Option<AccessVariants>,
-    pub(crate) expressions: (Vec<Expression>, Option<Retry>),
+    pub(crate) body: Body,
 }
 
-pub enum RescueEnsureOrElse {
-    Ensure(Vec<Expression>),
-    Else(Vec<Expression>),
-}
-
-pub struct Retry;
-
-pub struct BEGINBlock(pub(crate) Vec<Expression>);
-pub struct ENDBlock(pub(crate) Vec<Expression>);
+pub struct BEGINBlock(pub(crate) Body);
+pub struct ENDBlock(pub(crate) Body);
 
 pub enum FlipFlopVariants {
     Inclusive(FlipFlop),
@@ -677,10 +2840,65 @@ pub enum FlipFlopVariants {
 pub struct FlipFlop {
     pub(crate) flip: Box<Expression>,
     pub(crate) flop: Box<Expression>,
-    pub(crate) expressions: Vec<Expression>,
+    pub(crate) body: Body,
 }
 
 pub struct RegularExpressionMatch {
     pub(crate) regex: RegularExpression,
     pub(crate) expression: Box<Expression>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_comment_parse() {
+        assert_eq!(
+            MagicComment::parse(" frozen_string_literal: true"),
+            Some(MagicComment::FrozenStringLiteral(true))
+        );
+        assert_eq!(
+            MagicComment::parse(" Frozen-String-Literal: FALSE"),
+            Some(MagicComment::FrozenStringLiteral(false))
+        );
+        assert_eq!(
+            MagicComment::parse(" coding: utf-8"),
+            Some(MagicComment::Encoding("utf-8".to_string()))
+        );
+        assert_eq!(
+            MagicComment::parse(" -*- mode: ruby; encoding: ascii-8bit -*-"),
+            Some(MagicComment::Encoding("ascii-8bit".to_string()))
+        );
+        assert_eq!(
+            MagicComment::parse(" shareable_constant_value: experimental_copy"),
+            Some(MagicComment::ShareableConstantValue(
+                ShareableConstantValue::ExperimentalCopy
+            ))
+        );
+        assert_eq!(
+            MagicComment::parse(" typed: strict"),
+            Some(MagicComment::Typed(TypedSigil::Strict))
+        );
+    }
+
+    #[test]
+    fn magic_comment_parse_rejects() {
+        assert_eq!(MagicComment::parse(" frozen_string_literal: yes"), None);
+        assert_eq!(MagicComment::parse(" encoding:"), None);
+        assert_eq!(MagicComment::parse(" typed: very strict"), None);
+        assert_eq!(MagicComment::parse(" -*- coding: utf-8"), None);
+        assert_eq!(MagicComment::parse(" just a comment"), None);
+    }
+
+    #[test]
+    fn set_magic_comment_replaces_same_kind() {
+        let mut root = Root::new(Vec::new());
+        root.set_magic_comment(MagicComment::FrozenStringLiteral(false));
+        root.set_magic_comment(MagicComment::Encoding("utf-8".to_string()));
+        root.set_magic_comment(MagicComment::FrozenStringLiteral(true));
+        assert_eq!(root.magic_comments().len(), 2);
+        assert_eq!(root.frozen_string_literal(), Some(true));
+        assert_eq!(root.encoding(), Some("utf-8"));
+    }
+}
@@ -10,41 +10,99 @@ impl<'a> Emitter for Access<'a> {
             ast::AccessVariants::Constant(c) => constant_variants(c),
             ast::AccessVariants::GlobalVariable(g) => global_variables(g),
             ast::AccessVariants::InstanceVariable(iv) => format!("@{}", iv.0),
-            ast::AccessVariants::LocalVariable(v) => v.0,
+            ast::AccessVariants::LocalVariable(v) => v.0.clone(),
             ast::AccessVariants::_Self => "self".to_string(),
         }
     }
 }
 
-fn constant_variants(c: &ast::ConstantVariants) -> String {
+pub(crate) fn constant_variants(c: &ast::ConstantVariants) -> String {
     match c {
-        ast::ConstantVariants::Encoding => "__ENCODING__".to_string(),
-        ast::ConstantVariants::File => "__FILE__".to_string(),
-        ast::ConstantVariants::Line => "__LINE__".to_string(),
+        ast::ConstantVariants::Encoding
+        | ast::ConstantVariants::File
+        | ast::ConstantVariants::Line => c.keyword().unwrap_or_default().to_string(),
         ast::ConstantVariants::Scoped(vc) => vc
             .iter()
-            .map(|cons| cons.0)
+            .map(|cons| cons.0.clone())
             .collect::<Vec<String>>()
             .join("::"),
         ast::ConstantVariants::TopLevel(tlc) => format!("::{}", tlc.0),
-        ast::ConstantVariants::Unscoped(uc) => uc.0,
+        ast::ConstantVariants::Unscoped(uc) => uc.0.clone(),
     }
 }
 
-fn global_variables(g: &ast::GlobalVariable) -> String {
-    match g {
-        ast::GlobalVariable::Ampersand => "$&".to_string(),
-        ast::GlobalVariable::Aposthrope => "$'".to_string(),
-        ast::GlobalVariable::AtSymbol => "$@".to_string(),
-        ast::GlobalVariable::Backtick => "$`".to_string(),
-        ast::GlobalVariable::Bang => "$!".to_string(),
-        ast::GlobalVariable::Colon => "$:".to_string(),
-        ast::GlobalVariable::Dollar => "$$".to_string(),
-        ast::GlobalVariable::NthReference(i) => format!("${}", i.0),
-        ast::GlobalVariable::Plain(pv) => format!("${}", pv.0),
-        ast::GlobalVariable::Plus => "$+".to_string(),
-        ast::GlobalVariable::QuestionMark => "$?".to_string(),
-        ast::GlobalVariable::Splat => "$*".to_string(),
-        ast::GlobalVariable::Tilde => "$~".to_string(),
+/// Emit global variable as spelled by `GlobalVariable::spelling`.
+pub(crate) fn global_variables(g: &ast::GlobalVariable) -> String {
+    format!("${}", g.spelling())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_variables_round_trip() {
+        let names = [
+            "$foo",
+            "$LOAD_PATH",
+            "$DEBUG",
+            "$stdout",
+            "$1",
+            "$12",
+            "$-w",
+            "$-0",
+            "$:",
+            "$*",
+            "$?",
+            "$$",
+            "$~",
+            "$!",
+            "$@",
+            "$;",
+            "$,",
+            "$/",
+            "$\\",
+            "$.",
+            "$_",
+            "$<",
+            "$>",
+            "$0",
+            "$\"",
+            "$&",
+            "$+",
+            "$`",
+            "$'",
+        ];
+        for name in &names {
+            let global = ast::GlobalVariable::parse(name).unwrap();
+            assert_eq!(global_variables(&global), *name);
+        }
+    }
+
+    #[test]
+    fn global_variable_kinds() {
+        assert!(matches!(
+            ast::GlobalVariable::parse("$LOAD_PATH"),
+            Some(ast::GlobalVariable::Plain(_))
+        ));
+        assert!(matches!(
+            ast::GlobalVariable::parse("$1"),
+            Some(ast::GlobalVariable::NthReference(_))
+        ));
+        assert!(matches!(
+            ast::GlobalVariable::parse("$&"),
+            Some(ast::GlobalVariable::BackReference(
+                ast::BackReference::Ampersand
+            ))
+        ));
+    }
+
+    #[test]
+    fn global_variable_parse_rejects() {
+        for name in &[
+            "foo", "$", "$01", "$1a", "$-", "$-ww", "$foo-bar", "$%", "$a b",
+        ] {
+            assert!(ast::GlobalVariable::parse(name).is_none(), "{}", name);
+        }
     }
 }
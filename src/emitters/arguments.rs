@@ -0,0 +1,178 @@
+use super::expression::Expression;
+use super::Emitter;
+use crate::ast;
+
+pub struct FormalArgument<'f>(pub(crate) &'f ast::FormalArgument);
+
+impl<'f> Emitter for FormalArgument<'f> {
+    fn emit(&self) -> String {
+        let ast::FormalArgument(params) = self.0;
+        params
+            .iter()
+            .map(parameter)
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+fn parameter(param: &ast::Parameter) -> String {
+    let name = |v: &Option<ast::Variable>| v.as_ref().map_or(String::new(), |v| v.0.clone());
+    match param {
+        ast::Parameter::Required(v) => v.0.clone(),
+        ast::Parameter::Decomposition(da) => decomposed_argument(da),
+        ast::Parameter::Optional(v, exp) => format!("{} = {}", v.0, Expression(exp).emit()),
+        ast::Parameter::Rest(v) => format!("*{}", name(v)),
+        ast::Parameter::KeywordRequired(v) => format!("{}:", v.0),
+        ast::Parameter::KeywordOptional(v, exp) => format!("{}: {}", v.0, Expression(exp).emit()),
+        ast::Parameter::KeywordRest(v) => format!("**{}", name(v)),
+        ast::Parameter::NoKeywords => "**nil".to_string(),
+        ast::Parameter::Block(v) => format!("&{}", name(v)),
+        ast::Parameter::ForwardArguments => "...".to_string(),
+    }
+}
+
+fn decomposed_argument(da: &ast::DecomposedArgument) -> String {
+    let ast::DecomposedArgument(elts) = da;
+    format!(
+        "({})",
+        elts.iter()
+            .map(|elt| match elt {
+                ast::DecomposedArgumentVariants::Plain(v) => v.0.clone(),
+                ast::DecomposedArgumentVariants::Nested(nda) => decomposed_argument(nda),
+                ast::DecomposedArgumentVariants::Splat(v) => {
+                    format!("*{}", v.as_ref().map_or(String::new(), |v| v.0.clone()))
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(name: &str) -> ast::Variable {
+        ast::Variable(name.to_string())
+    }
+
+    fn emit(params: Vec<ast::Parameter>) -> Option<String> {
+        ast::FormalArgument::new(params).map(|args| FormalArgument(&args).emit())
+    }
+
+    fn one() -> ast::Expression {
+        ast::Expression::Literal(ast::ValueVariants::Integer(ast::IntegerLiteral(1)))
+    }
+
+    #[test]
+    fn parameter_order() {
+        let all = vec![
+            ast::Parameter::Required(v("a")),
+            ast::Parameter::Optional(v("b"), one()),
+            ast::Parameter::Rest(Some(v("c"))),
+            ast::Parameter::Required(v("d")),
+            ast::Parameter::KeywordRequired(v("e")),
+            ast::Parameter::KeywordOptional(v("f"), one()),
+            ast::Parameter::KeywordRest(Some(v("g"))),
+            ast::Parameter::Block(Some(v("h"))),
+        ];
+        assert_eq!(
+            emit(all).as_deref(),
+            Some("a, b = 1, *c, d, e:, f: 1, **g, &h")
+        );
+        let no_keywords = vec![ast::Parameter::Rest(None), ast::Parameter::NoKeywords];
+        assert_eq!(emit(no_keywords).as_deref(), Some("*, **nil"));
+        let forward = vec![
+            ast::Parameter::Required(v("a")),
+            ast::Parameter::Optional(v("b"), one()),
+            ast::Parameter::ForwardArguments,
+        ];
+        assert_eq!(emit(forward).as_deref(), Some("a, b = 1, ..."));
+    }
+
+    #[test]
+    fn post_parameters() {
+        // Required parameter after optional one is a post parameter.
+        let post = vec![
+            ast::Parameter::Optional(v("a"), one()),
+            ast::Parameter::Required(v("b")),
+        ];
+        assert_eq!(emit(post).as_deref(), Some("a = 1, b"));
+        let decomposed = vec![
+            ast::Parameter::Rest(Some(v("rest"))),
+            ast::Parameter::Decomposition(ast::DecomposedArgument(vec![
+                ast::DecomposedArgumentVariants::Plain(v("a")),
+                ast::DecomposedArgumentVariants::Nested(ast::DecomposedArgument(vec![
+                    ast::DecomposedArgumentVariants::Plain(v("b")),
+                    ast::DecomposedArgumentVariants::Splat(Some(v("c"))),
+                ])),
+                ast::DecomposedArgumentVariants::Splat(None),
+            ])),
+            ast::Parameter::Required(v("last")),
+        ];
+        assert_eq!(
+            emit(decomposed).as_deref(),
+            Some("*rest, (a, (b, *c), *), last")
+        );
+    }
+
+    #[test]
+    fn parameter_order_rejects() {
+        let rejected = vec![
+            // Optional after post parameter.
+            vec![
+                ast::Parameter::Rest(None),
+                ast::Parameter::Required(v("a")),
+                ast::Parameter::Optional(v("b"), one()),
+            ],
+            // Optional after rest.
+            vec![
+                ast::Parameter::Rest(None),
+                ast::Parameter::Optional(v("a"), one()),
+            ],
+            vec![ast::Parameter::Rest(None), ast::Parameter::Rest(None)],
+            vec![
+                ast::Parameter::KeywordRequired(v("a")),
+                ast::Parameter::Required(v("b")),
+            ],
+            vec![
+                ast::Parameter::NoKeywords,
+                ast::Parameter::KeywordRest(None),
+            ],
+            vec![
+                ast::Parameter::Block(None),
+                ast::Parameter::Required(v("a")),
+            ],
+            // `...` after a block, rest or keyword parameter.
+            vec![
+                ast::Parameter::Block(None),
+                ast::Parameter::ForwardArguments,
+            ],
+            vec![ast::Parameter::Rest(None), ast::Parameter::ForwardArguments],
+            vec![
+                ast::Parameter::KeywordRequired(v("a")),
+                ast::Parameter::ForwardArguments,
+            ],
+            vec![
+                ast::Parameter::ForwardArguments,
+                ast::Parameter::Block(None),
+            ],
+        ];
+        for params in rejected {
+            assert!(ast::FormalArgument::new(params).is_none());
+        }
+    }
+
+    #[test]
+    fn block_parameters() {
+        let forward = ast::FormalArgument::new(vec![ast::Parameter::ForwardArguments]).unwrap();
+        assert!(ast::BlockParameters::explicit(forward, vec![], false).is_none());
+        // `**nil` is allowed in block parameters, e.g. `proc { |a, **nil| }`.
+        let no_keywords = ast::FormalArgument::new(vec![
+            ast::Parameter::Required(v("a")),
+            ast::Parameter::NoKeywords,
+        ])
+        .unwrap();
+        assert!(ast::BlockParameters::explicit(no_keywords, vec![], false).is_some());
+    }
+}
@@ -0,0 +1,196 @@
+use super::access::{constant_variants, global_variables, Access};
+use super::expression::element;
+use super::literals::array_interpolations;
+use super::Emitter;
+use crate::ast;
+
+pub struct Assignment<'a>(pub(crate) &'a ast::AssignmentVariants);
+
+impl<'a> Emitter for Assignment<'a> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::AssignmentVariants::ToLocalVariable(v, exp) => {
+                format!("{} = {}", v.0, element(exp))
+            }
+            ast::AssignmentVariants::ToInstanceVariable(v, exp) => {
+                format!("@{} = {}", v.0, element(exp))
+            }
+            ast::AssignmentVariants::ToClassVariable(v, exp) => {
+                format!("@@{} = {}", v.0, element(exp))
+            }
+            ast::AssignmentVariants::ToGlobalVariable(g, exp) => {
+                format!("{} = {}", global_variables(g), element(exp))
+            }
+            ast::AssignmentVariants::ToConstant(c, exp) => {
+                format!("{} = {}", constant_variants(c), element(exp))
+            }
+            ast::AssignmentVariants::MultipleAssignment(mlhs, mrhs) => format!(
+                "{} = {}",
+                multiple_left_hand_side(mlhs),
+                array_interpolations(&mrhs.0)
+            ),
+            ast::AssignmentVariants::BinaryOperator(op, acc, exp) => {
+                format!("{} {}= {}", Access(acc).emit(), op.spelling(), element(exp))
+            }
+            ast::AssignmentVariants::LogicalOperator(op, acc, exp) => {
+                format!("{} {}= {}", Access(acc).emit(), op.spelling(), element(exp))
+            }
+        }
+    }
+}
+
+/// Emit comma separated targets, a single target without splat
+/// needs trailing comma to be destructured, e.g. `a, = list`.
+pub(crate) fn multiple_left_hand_side(mlhs: &ast::MultipleLeftHandSide) -> String {
+    let targets = mlhs
+        .0
+        .iter()
+        .map(multiple_left_hand_side_element)
+        .collect::<Vec<String>>()
+        .join(", ");
+    match mlhs.0.as_slice() {
+        [ast::MultipleLeftHandSideElement::Splat(_)] => targets,
+        [_] => format!("{},", targets),
+        _ => targets,
+    }
+}
+
+pub(crate) fn multiple_left_hand_side_element(elt: &ast::MultipleLeftHandSideElement) -> String {
+    match elt {
+        ast::MultipleLeftHandSideElement::PlainAccess(acc) => Access(acc).emit(),
+        ast::MultipleLeftHandSideElement::AttributeAccess(attr) => {
+            format!("{}.{}", Access(&attr.receiver).emit(), attr.attribute.0)
+        }
+        ast::MultipleLeftHandSideElement::IndexAccess(idx) => format!(
+            "{}[{}]",
+            Access(&idx.receiver).emit(),
+            array_interpolations(&idx.index)
+        ),
+        ast::MultipleLeftHandSideElement::Nested(mlhs) => {
+            format!("({})", multiple_left_hand_side(mlhs))
+        }
+        ast::MultipleLeftHandSideElement::Splat(target) => format!(
+            "*{}",
+            target
+                .as_ref()
+                .map_or(String::new(), |t| multiple_left_hand_side_element(t))
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn local(name: &str) -> ast::MultipleLeftHandSideElement {
+        ast::MultipleLeftHandSideElement::PlainAccess(ast::AccessVariants::LocalVariable(
+            ast::Variable(name.to_string()),
+        ))
+    }
+
+    fn masgn(
+        targets: Vec<ast::MultipleLeftHandSideElement>,
+        values: Vec<ast::Expression>,
+    ) -> ast::Expression {
+        ast::Expression::Assignment(ast::AssignmentVariants::MultipleAssignment(
+            ast::MultipleLeftHandSide::new(targets).unwrap(),
+            ast::MultipleRightHandSide::new(
+                values
+                    .into_iter()
+                    .map(ast::ArrayInterpolation::Expression)
+                    .collect(),
+            )
+            .unwrap(),
+        ))
+    }
+
+    fn splat(target: Option<ast::MultipleLeftHandSideElement>) -> ast::MultipleLeftHandSideElement {
+        ast::MultipleLeftHandSideElement::Splat(target.map(Box::new))
+    }
+
+    #[test]
+    fn single_target_keeps_trailing_comma() {
+        assert_eq!(emit(&masgn(vec![local("a")], vec![var("x")])), "a, = x");
+        assert_eq!(
+            emit(&masgn(vec![splat(Some(local("a")))], vec![var("x")])),
+            "*a = x"
+        );
+    }
+
+    #[test]
+    fn multiple_targets() {
+        assert_eq!(
+            emit(&masgn(
+                vec![local("a"), local("b")],
+                vec![var("b"), var("a")]
+            )),
+            "a, b = b, a"
+        );
+        assert_eq!(
+            emit(&masgn(
+                vec![local("a"), splat(Some(local("b")))],
+                vec![var("list")]
+            )),
+            "a, *b = list"
+        );
+        assert_eq!(
+            emit(&masgn(vec![local("a"), splat(None)], vec![var("list")])),
+            "a, * = list"
+        );
+    }
+
+    #[test]
+    fn nested_targets() {
+        let nested = |targets| {
+            ast::MultipleLeftHandSideElement::Nested(
+                ast::MultipleLeftHandSide::new(targets).unwrap(),
+            )
+        };
+        assert_eq!(
+            emit(&masgn(
+                vec![nested(vec![local("a"), local("b")]), local("c")],
+                vec![var("x")]
+            )),
+            "(a, b), c = x"
+        );
+        assert_eq!(
+            emit(&masgn(
+                vec![local("a"), nested(vec![local("b")])],
+                vec![var("x")]
+            )),
+            "a, (b,) = x"
+        );
+    }
+
+    #[test]
+    fn attribute_and_index_targets() {
+        let receiver = || ast::AccessVariants::_Self;
+        let attribute =
+            ast::MultipleLeftHandSideElement::AttributeAccess(ast::AccessAttributeVariants {
+                receiver: receiver(),
+                attribute: ast::Variable("name".to_string()),
+            });
+        let index = ast::MultipleLeftHandSideElement::IndexAccess(ast::AccessIndexVariants {
+            receiver: ast::AccessVariants::LocalVariable(ast::Variable("list".to_string())),
+            index: vec![ast::ArrayInterpolation::Expression(int(0))],
+        });
+        assert_eq!(
+            emit(&masgn(vec![attribute, index], vec![int(1), int(2)])),
+            "self.name, list[0] = 1, 2"
+        );
+    }
+
+    #[test]
+    fn invalid_targets() {
+        assert!(ast::MultipleLeftHandSide::new(vec![]).is_none());
+        assert!(ast::MultipleLeftHandSide::new(vec![splat(None), splat(None)]).is_none());
+        assert!(ast::MultipleRightHandSide::new(vec![]).is_none());
+    }
+
+    #[test]
+    fn parenthesized_as_argument() {
+        let value = masgn(vec![local("a"), local("b")], vec![var("x")]);
+        assert_eq!(emit(&call("p", vec![value])), "p((a, b = x))");
+    }
+}
@@ -0,0 +1,471 @@
+use super::arguments::FormalArgument;
+use super::definitions::MethodName;
+use super::expression::primary;
+use super::{body, definition_body, single_line, Emitter, MAX_LINE_WIDTH};
+use crate::ast;
+
+pub struct Block<'b>(pub(crate) &'b ast::BlockArgument);
+
+impl<'b> Emitter for Block<'b> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Variable(v)) => {
+                format!("&{}", v.0)
+            }
+            ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Symbol(name)) => {
+                format!("&:{}", MethodName(name).emit())
+            }
+            ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Expression(pexp)) => {
+                format!("&{}", ProcExpression(pexp).emit())
+            }
+            ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::ToProc(exp)) => {
+                format!("&{}", primary(exp))
+            }
+            ast::BlockArgument::AnonymousPass => "&".to_string(),
+            ast::BlockArgument::BeginBlock(blk) => format!(" {}", block_literal(blk)),
+        }
+    }
+}
+
+/// Emit block literal with delimiters following its style,
+/// e.g. `{ |x| x }` or `do |x| ... end`.
+pub(crate) fn block_literal(blk: &ast::Block) -> String {
+    delimited_body(&block_parameters(&blk.params), blk)
+}
+
+/// Emit block body between braces or `do ... end`,
+/// `params` is emitted right after the opening delimiter.
+fn delimited_body(params: &str, blk: &ast::Block) -> String {
+    // Rescue and ensure clauses are only allowed in `do ... end`.
+    let clauses = matches!(
+        blk.body.0.as_slice(),
+        [ast::Expression::ExceptionHandling(
+            ast::ExceptionHandlingVariants::Implicit(_)
+        )]
+    );
+    let line = match blk.body.0.as_slice() {
+        [] if params.is_empty() => Some("{}".to_string()),
+        [] => Some(format!("{{{} }}", params)),
+        _ => single_line(&blk.body.0).map(|stmt| format!("{{{} {} }}", params, stmt)),
+    };
+    match (&blk.style, line) {
+        (ast::BlockStyle::Auto, Some(line)) if !clauses && line.len() <= MAX_LINE_WIDTH => line,
+        (ast::BlockStyle::Braces, Some(line)) if !clauses => line,
+        (ast::BlockStyle::Braces, _) if !clauses => {
+            format!("{{{}\n{}}}", params, body(&blk.body.0))
+        }
+        _ => format!("do{}\n{}end", params, definition_body(&blk.body.0)),
+    }
+}
+
+/// Whether the expression itself has a block emitted as `do ... end`,
+/// e.g. `foo do ... end` or `lambda do ... end`, nested expressions are not checked.
+pub(crate) fn has_do_block(exp: &ast::Expression) -> bool {
+    let pexp = match exp {
+        ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(sm))
+        | ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(_, sm)) => {
+            match &sm.args.1 {
+                Some(ast::BlockArgument::BeginBlock(blk)) => {
+                    return block_literal(blk).starts_with("do")
+                }
+                Some(ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Expression(pexp))) => {
+                    pexp
+                }
+                _ => return false,
+            }
+        }
+        ast::Expression::Proc(pexp) => pexp,
+        _ => return false,
+    };
+    match pexp {
+        ast::ProcExpressionVariants::Proc(blk) | ast::ProcExpressionVariants::Lambda(blk) => {
+            block_literal(blk).starts_with("do")
+        }
+        ast::ProcExpressionVariants::Stubby(blk) => delimited_body("", blk).starts_with("do"),
+    }
+}
+
+pub struct ProcExpression<'p>(pub(crate) &'p ast::ProcExpressionVariants);
+
+impl<'p> Emitter for ProcExpression<'p> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::ProcExpressionVariants::Proc(blk) => format!("proc {}", block_literal(blk)),
+            ast::ProcExpressionVariants::Lambda(blk) => format!("lambda {}", block_literal(blk)),
+            ast::ProcExpressionVariants::Stubby(blk) => format!(
+                "->{} {}",
+                lambda_parameters(&blk.params),
+                delimited_body("", blk)
+            ),
+        }
+    }
+}
+
+/// Emit block parameters between pipes, including block-local variables,
+/// blocks with implicit, numbered or `it` parameter have no pipes.
+fn block_parameters(params: &ast::BlockParameters) -> String {
+    match params {
+        ast::BlockParameters::Explicit(args, locals, trailing_comma) => {
+            format!(
+                " |{}|",
+                parameters_with_locals(args, locals, *trailing_comma)
+            )
+        }
+        _ => String::new(),
+    }
+}
+
+/// Emit stubby lambda parameters, e.g. `->(a; tmp)`.
+/// Trailing comma is not allowed there and it makes no difference for lambdas,
+/// so it's dropped.
+fn lambda_parameters(params: &ast::BlockParameters) -> String {
+    match params {
+        ast::BlockParameters::Explicit(args, locals, _) => {
+            format!("({})", parameters_with_locals(args, locals, false))
+        }
+        _ => String::new(),
+    }
+}
+
+fn parameters_with_locals(
+    args: &ast::FormalArgument,
+    locals: &[ast::Variable],
+    trailing_comma: bool,
+) -> String {
+    let mut args = FormalArgument(args).emit();
+    if trailing_comma {
+        args.push(',');
+    }
+    if locals.is_empty() {
+        return args;
+    }
+    format!(
+        "{}; {}",
+        args,
+        locals
+            .iter()
+            .map(|v| v.0.clone())
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn each(params: ast::BlockParameters, expressions: Vec<ast::Expression>) -> ast::Expression {
+        let block = ast::Block {
+            params,
+            body: body(expressions),
+            style: ast::BlockStyle::Auto,
+        };
+        ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(
+            Box::new(var("list")),
+            method(
+                "each",
+                ast::SendMethodArgument(vec![], Some(ast::BlockArgument::BeginBlock(block))),
+            ),
+        ))
+    }
+
+    fn explicit(names: &[&str], locals: &[&str], trailing_comma: bool) -> ast::BlockParameters {
+        ast::BlockParameters::explicit(
+            params(names),
+            locals
+                .iter()
+                .map(|name| ast::Variable(name.to_string()))
+                .collect(),
+            trailing_comma,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn block_parameters() {
+        let implicit = each(ast::BlockParameters::Implicit, vec![var("x")]);
+        assert_eq!(emit(&implicit), "list.each { x }");
+        let pipes = each(explicit(&[], &[], false), vec![var("x")]);
+        assert_eq!(emit(&pipes), "list.each { || x }");
+        let locals = each(explicit(&["a"], &["tmp", "acc"], false), vec![var("a")]);
+        assert_eq!(emit(&locals), "list.each { |a; tmp, acc| a }");
+        let trailing = each(explicit(&["a"], &[], true), vec![var("a")]);
+        assert_eq!(emit(&trailing), "list.each { |a,| a }");
+    }
+
+    #[test]
+    fn implicit_parameters() {
+        let sum = binary(var("_1"), ast::BinaryOperator::Add, var("_2"));
+        let numbered = ast::BlockParameters::Numbered(ast::NumberedParameter::new(2).unwrap());
+        assert_eq!(emit(&each(numbered, vec![sum])), "list.each { _1 + _2 }");
+        let double = binary(var("it"), ast::BinaryOperator::Multiply, int(2));
+        assert_eq!(
+            emit(&each(ast::BlockParameters::It, vec![double])),
+            "list.each { it * 2 }"
+        );
+    }
+
+    #[test]
+    fn parameter_versions() {
+        assert!(ast::NumberedParameter::new(0).is_none());
+        assert!(ast::NumberedParameter::new(10).is_none());
+        let numbered = ast::BlockParameters::Numbered(ast::NumberedParameter::new(1).unwrap());
+        assert_eq!(numbered.minimum_version(), Some(ast::RubyVersion(2, 7)));
+        assert_eq!(
+            ast::BlockParameters::It.minimum_version(),
+            Some(ast::RubyVersion(3, 4))
+        );
+        assert_eq!(ast::BlockParameters::Implicit.minimum_version(), None);
+    }
+
+    #[test]
+    fn trailing_comma_follows_required_parameter() {
+        let optional = ast::FormalArgument(vec![ast::Parameter::Optional(
+            ast::Variable("a".to_string()),
+            int(1),
+        )]);
+        assert!(ast::BlockParameters::explicit(optional, vec![], true).is_none());
+        assert!(ast::BlockParameters::explicit(params(&[]), vec![], true).is_none());
+    }
+
+    fn with_block(block: ast::BlockArgument) -> ast::Expression {
+        ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(
+            Box::new(var("list")),
+            method("map", ast::SendMethodArgument(vec![], Some(block))),
+        ))
+    }
+
+    fn block(expressions: Vec<ast::Expression>, style: ast::BlockStyle) -> ast::BlockArgument {
+        ast::BlockArgument::BeginBlock(ast::Block {
+            params: explicit(&["x"], &[], false),
+            body: body(expressions),
+            style,
+        })
+    }
+
+    #[test]
+    fn block_styles() {
+        let short = || vec![send(var("x"), "to_s", vec![])];
+        let long = || vec![call("foo", vec![var("x")]), call("bar", vec![var("x")])];
+        assert_eq!(
+            emit(&with_block(block(short(), ast::BlockStyle::Auto))),
+            "list.map { |x| x.to_s }"
+        );
+        assert_eq!(
+            emit(&with_block(block(long(), ast::BlockStyle::Auto))),
+            "list.map do |x|\n  foo(x)\n  bar(x)\nend"
+        );
+        assert_eq!(
+            emit(&with_block(block(long(), ast::BlockStyle::Braces))),
+            "list.map { |x|\n  foo(x)\n  bar(x)\n}"
+        );
+        assert_eq!(
+            emit(&with_block(block(short(), ast::BlockStyle::DoEnd))),
+            "list.map do |x|\n  x.to_s\nend"
+        );
+        assert_eq!(
+            emit(&with_block(block(vec![], ast::BlockStyle::Auto))),
+            "list.map { |x| }"
+        );
+        let wide = vec![call(&"x".repeat(80), vec![])];
+        assert_eq!(
+            emit(&with_block(block(wide, ast::BlockStyle::Auto))),
+            format!("list.map do |x|\n  {}\nend", "x".repeat(80))
+        );
+    }
+
+    #[test]
+    fn rescue_clauses_need_do_end() {
+        let handling =
+            ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Implicit(
+                ast::ExceptionHandling::new(body(vec![var("x")]), vec![], None, Some(body(vec![])))
+                    .unwrap(),
+            ));
+        assert_eq!(
+            emit(&with_block(block(vec![handling], ast::BlockStyle::Braces))),
+            "list.map do |x|\n  x\nensure\nend"
+        );
+    }
+
+    #[test]
+    fn block_pass() {
+        let pass = |arg| emit(&with_block(ast::BlockArgument::Pass(arg)));
+        assert_eq!(
+            pass(ast::ProcAsArgumentVariants::Variable(ast::Variable(
+                "blk".to_string()
+            ))),
+            "list.map(&blk)"
+        );
+        assert_eq!(
+            pass(ast::ProcAsArgumentVariants::Symbol(
+                ast::MethodName::parse("to_s").unwrap()
+            )),
+            "list.map(&:to_s)"
+        );
+        assert_eq!(
+            pass(ast::ProcAsArgumentVariants::Symbol(
+                ast::MethodName::parse("+@").unwrap()
+            )),
+            "list.map(&:+@)"
+        );
+        assert_eq!(
+            pass(ast::ProcAsArgumentVariants::ToProc(Box::new(call(
+                "method",
+                vec![symbol("puts")]
+            )))),
+            "list.map(&method(:puts))"
+        );
+        let args = ast::SendMethodArgument(
+            vec![ast::ArgumentVariants::Expression(int(1))],
+            Some(ast::BlockArgument::Pass(
+                ast::ProcAsArgumentVariants::Variable(ast::Variable("blk".to_string())),
+            )),
+        );
+        let call =
+            ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method("foo", args)));
+        assert_eq!(emit(&call), "foo(1, &blk)");
+    }
+
+    #[test]
+    fn do_block_in_loop_condition() {
+        let long = vec![call("foo", vec![var("x")]), call("bar", vec![var("x")])];
+        let condition = with_block(block(long, ast::BlockStyle::Auto));
+        let loop_ = ast::Expression::Loop(ast::LoopVariants::PreCondition(
+            ast::LoopConditionVariants::While(ast::LoopStruct {
+                condition: Box::new(condition),
+                body: body(vec![var("y")]),
+                form: ast::ModifierForm::Block,
+            }),
+        ));
+        assert_eq!(
+            emit(&loop_),
+            "while (list.map do |x|\n  foo(x)\n  bar(x)\nend)\n  y\nend"
+        );
+    }
+
+    #[test]
+    fn do_block_in_argument_binds_to_its_call() {
+        let long = vec![call("foo", vec![var("x")]), call("bar", vec![var("x")])];
+        let puts = call("puts", vec![with_block(block(long, ast::BlockStyle::Auto))]);
+        assert_eq!(
+            emit(&puts),
+            "puts(list.map do |x|\n  foo(x)\n  bar(x)\nend)"
+        );
+    }
+
+    fn literal(params: ast::BlockParameters, expressions: Vec<ast::Expression>) -> ast::Block {
+        ast::Block {
+            params,
+            body: body(expressions),
+            style: ast::BlockStyle::Auto,
+        }
+    }
+
+    fn proc_expression(pexp: ast::ProcExpressionVariants) -> String {
+        emit(&ast::Expression::Proc(pexp))
+    }
+
+    #[test]
+    fn procs_and_lambdas() {
+        let blk = || literal(explicit(&["a"], &[], false), vec![var("a")]);
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Proc(blk())),
+            "proc { |a| a }"
+        );
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Lambda(blk())),
+            "lambda { |a| a }"
+        );
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(blk())),
+            "->(a) { a }"
+        );
+        let implicit = literal(ast::BlockParameters::Implicit, vec![var("x")]);
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(implicit)),
+            "-> { x }"
+        );
+    }
+
+    #[test]
+    fn stubby_lambda_parameters() {
+        let optional = ast::FormalArgument::new(vec![
+            ast::Parameter::Required(ast::Variable("a".to_string())),
+            ast::Parameter::Optional(ast::Variable("b".to_string()), int(1)),
+        ])
+        .unwrap();
+        let params = ast::BlockParameters::explicit(optional, vec![], false).unwrap();
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(literal(
+                params,
+                vec![var("a")]
+            ))),
+            "->(a, b = 1) { a }"
+        );
+        let decomposed = ast::FormalArgument(vec![ast::Parameter::Decomposition(
+            ast::DecomposedArgument(vec![
+                ast::DecomposedArgumentVariants::Plain(ast::Variable("a".to_string())),
+                ast::DecomposedArgumentVariants::Plain(ast::Variable("b".to_string())),
+            ]),
+        )]);
+        let params =
+            ast::BlockParameters::explicit(decomposed, vec![ast::Variable("t".to_string())], false)
+                .unwrap();
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(literal(
+                params,
+                vec![var("a")]
+            ))),
+            "->((a, b); t) { a }"
+        );
+        // Trailing comma makes no difference for lambdas.
+        let trailing = literal(explicit(&["a"], &[], true), vec![var("a")]);
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(trailing)),
+            "->(a) { a }"
+        );
+        let multiple = literal(ast::BlockParameters::Implicit, vec![var("x"), var("y")]);
+        assert_eq!(
+            proc_expression(ast::ProcExpressionVariants::Stubby(multiple)),
+            "-> do\n  x\n  y\nend"
+        );
+    }
+
+    #[test]
+    fn proc_conversions() {
+        let blk = || literal(ast::BlockParameters::Implicit, vec![var("x")]);
+        let stubby = ast::ProcExpressionVariants::Lambda(blk()).into_stubby();
+        assert!(stubby.is_lambda());
+        assert_eq!(proc_expression(stubby), "-> { x }");
+        let lambda = ast::ProcExpressionVariants::Stubby(blk()).into_lambda();
+        assert_eq!(proc_expression(lambda), "lambda { x }");
+        let proc_ = ast::ProcExpressionVariants::Proc(blk()).into_stubby();
+        assert!(!proc_.is_lambda());
+        assert_eq!(proc_expression(proc_), "proc { x }");
+    }
+
+    #[test]
+    fn block_conversions() {
+        let blk = |expressions| {
+            ast::BlockArgument::BeginBlock(literal(ast::BlockParameters::Implicit, expressions))
+        };
+        let pass = blk(vec![var("x")]).into_pass();
+        assert_eq!(emit(&with_block(pass)), "list.map(&proc { x })");
+        // `break` out of the block is kept as block.
+        let breaks = blk(vec![ast::Expression::Break(vec![])]).into_pass();
+        assert_eq!(emit(&with_block(breaks)), "list.map { break }");
+        let pass = blk(vec![var("x")]).into_pass().into_block();
+        assert_eq!(emit(&with_block(pass)), "list.map { x }");
+        let lambda = ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Expression(
+            ast::ProcExpressionVariants::Stubby(literal(
+                ast::BlockParameters::Implicit,
+                vec![var("x")],
+            )),
+        ));
+        assert_eq!(
+            emit(&with_block(lambda.into_block())),
+            "list.map(&-> { x })"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use super::expression::{primary, Expression};
+use super::{block_form, body, Emitter, MAX_LINE_WIDTH};
+use crate::ast;
+
+pub struct Branching<'b>(pub(crate) &'b ast::BranchingVariants);
+
+impl<'b> Emitter for Branching<'b> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::BranchingVariants::If(ast::BranchingIfVariants::WithoutElse(bi)) => {
+                block_form("if", &bi.condition, &bi.iftrue.0)
+            }
+            ast::BranchingVariants::If(biv) => format!("if {}end", if_branches(biv)),
+            ast::BranchingVariants::Unless(ast::BranchingUnlessVariants::WithoutElse(bu)) => {
+                block_form("unless", &bu.condition, &bu.iffalse.0)
+            }
+            ast::BranchingVariants::Unless(ast::BranchingUnlessVariants::WithElse(tb)) => {
+                format!(
+                    "unless {}\n{}else\n{}end",
+                    Expression(&tb.condition).emit(),
+                    body(&tb.iffalse.0),
+                    body(&tb.iftrue.0)
+                )
+            }
+        }
+    }
+}
+
+/// Emit `if` or `unless` without `else` in modifier form, see `super::modifier`.
+pub(crate) fn modifier(b: &ast::BranchingVariants) -> Option<String> {
+    match b {
+        ast::BranchingVariants::If(ast::BranchingIfVariants::WithoutElse(bi)) => {
+            super::modifier("if", &bi.condition, &bi.iftrue.0, &bi.form)
+        }
+        ast::BranchingVariants::Unless(ast::BranchingUnlessVariants::WithoutElse(bu)) => {
+            super::modifier("unless", &bu.condition, &bu.iffalse.0, &bu.form)
+        }
+        _ => None,
+    }
+}
+
+/// Emit `if` branches starting from its condition, used for `elsif` chain.
+fn if_branches(biv: &ast::BranchingIfVariants) -> String {
+    match biv {
+        ast::BranchingIfVariants::WithoutElse(bi) => format!(
+            "{}\n{}",
+            Expression(&bi.condition).emit(),
+            body(&bi.iftrue.0)
+        ),
+        ast::BranchingIfVariants::WithElse(tb) => format!(
+            "{}\n{}else\n{}",
+            Expression(&tb.condition).emit(),
+            body(&tb.iftrue.0),
+            body(&tb.iffalse.0)
+        ),
+        ast::BranchingIfVariants::WithElsif(web) => format!(
+            "{}\n{}elsif {}",
+            Expression(&web.condition).emit(),
+            body(&web.iftrue.0),
+            if_branches(&web.elsif)
+        ),
+    }
+}
+
+pub struct TernaryBranching<'t>(pub(crate) &'t ast::TernaryBranching);
+
+impl<'t> Emitter for TernaryBranching<'t> {
+    fn emit(&self) -> String {
+        let tb = self.0;
+        let condition = primary(&tb.condition);
+        // Branches with comments are kept in block form.
+        if let ([iftrue], [iffalse]) = (tb.iftrue.0.as_slice(), tb.iffalse.0.as_slice()) {
+            if !matches!(iftrue, ast::Expression::Commented(..))
+                && !matches!(iffalse, ast::Expression::Commented(..))
+            {
+                let line = format!("{} ? {} : {}", condition, primary(iftrue), primary(iffalse));
+                if !line.contains('\n') && line.len() <= MAX_LINE_WIDTH {
+                    return line;
+                }
+            }
+        }
+        format!(
+            "if {}\n{}else\n{}end",
+            Expression(&tb.condition).emit(),
+            body(&tb.iftrue.0),
+            body(&tb.iffalse.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn ternary(
+        iftrue: Vec<ast::Expression>,
+        iffalse: Vec<ast::Expression>,
+    ) -> ast::TernaryBranching {
+        ast::TernaryBranching {
+            condition: Box::new(var("c")),
+            iftrue: body(iftrue),
+            iffalse: body(iffalse),
+        }
+    }
+
+    #[test]
+    fn multiple_statements() {
+        let branch = ast::Expression::Branching(ast::BranchingVariants::If(
+            ast::BranchingIfVariants::WithoutElse(ast::BranchingIf {
+                condition: Box::new(var("c")),
+                iftrue: body(vec![call("a", vec![]), call("b", vec![]), var("x")]),
+                form: ast::ModifierForm::Auto,
+            }),
+        ));
+        assert_eq!(emit(&branch), "if c\n  a\n  b\n  x\nend");
+        let branch = ast::Expression::Branching(ast::BranchingVariants::If(
+            ast::BranchingIfVariants::WithElse(ternary(vec![var("a"), var("b")], vec![var("x")])),
+        ));
+        assert_eq!(emit(&branch), "if c\n  a\n  b\nelse\n  x\nend");
+    }
+
+    #[test]
+    fn elsif_chain() {
+        let branch = ast::Expression::Branching(ast::BranchingVariants::If(
+            ast::BranchingIfVariants::WithElsif(ast::WithElsifBranching {
+                condition: Box::new(var("a")),
+                iftrue: body(vec![int(1)]),
+                elsif: Box::new(ast::BranchingIfVariants::WithElse(ast::TernaryBranching {
+                    condition: Box::new(var("b")),
+                    iftrue: body(vec![int(2)]),
+                    iffalse: body(vec![int(3), int(4)]),
+                })),
+            }),
+        ));
+        assert_eq!(
+            emit(&branch),
+            "if a\n  1\nelsif b\n  2\nelse\n  3\n  4\nend"
+        );
+    }
+
+    #[test]
+    fn unless_else() {
+        let branch = ast::Expression::Branching(ast::BranchingVariants::Unless(
+            ast::BranchingUnlessVariants::WithElse(ternary(vec![var("a")], vec![var("b")])),
+        ));
+        assert_eq!(emit(&branch), "unless c\n  b\nelse\n  a\nend");
+    }
+
+    #[test]
+    fn ternary_form() {
+        let short = ast::Expression::TernaryBranching(ternary(vec![int(1)], vec![int(2)]));
+        assert_eq!(emit(&short), "c ? 1 : 2");
+        let operands = ast::Expression::TernaryBranching(ternary(
+            vec![binary(var("a"), ast::BinaryOperator::Add, int(1))],
+            vec![var("b")],
+        ));
+        assert_eq!(emit(&operands), "c ? (a + 1) : b");
+        let long = "x".repeat(80);
+        let long = ast::Expression::TernaryBranching(ternary(vec![var(&long)], vec![int(2)]));
+        assert_eq!(
+            emit(&long),
+            format!("if c\n  {}\nelse\n  2\nend", "x".repeat(80))
+        );
+        let multiple =
+            ast::Expression::TernaryBranching(ternary(vec![int(1), int(2)], vec![int(3)]));
+        assert_eq!(emit(&multiple), "if c\n  1\n  2\nelse\n  3\nend");
+    }
+
+    #[test]
+    fn modifier_statements() {
+        let auto = if_(
+            var("bar"),
+            vec![call("foo", vec![])],
+            ast::ModifierForm::Auto,
+        );
+        assert_eq!(emit(&auto), "foo if bar");
+        let long = call(&"x".repeat(80), vec![]);
+        let auto = if_(var("bar"), vec![long], ast::ModifierForm::Auto);
+        assert_eq!(emit(&auto), format!("if bar\n  {}\nend", "x".repeat(80)));
+        let long = call(&"x".repeat(80), vec![]);
+        let modifier = if_(var("bar"), vec![long], ast::ModifierForm::Modifier);
+        assert_eq!(emit(&modifier), format!("{} if bar", "x".repeat(80)));
+        let block = if_(
+            var("bar"),
+            vec![call("foo", vec![])],
+            ast::ModifierForm::Block,
+        );
+        assert_eq!(emit(&block), "if bar\n  foo\nend");
+        let unless = ast::Expression::Branching(ast::BranchingVariants::Unless(
+            ast::BranchingUnlessVariants::WithoutElse(ast::BranchingUnless {
+                condition: Box::new(var("bar")),
+                iffalse: body(vec![call("foo", vec![])]),
+                form: ast::ModifierForm::Auto,
+            }),
+        ));
+        assert_eq!(emit(&unless), "foo unless bar");
+    }
+
+    #[test]
+    fn modifier_with_comment() {
+        let stmt = call("foo", vec![]).with_comments(ast::Comments::new(
+            vec![ast::CommentVariants::Line(" lead".to_string())],
+            Some(" trail".to_string()),
+        ));
+        let modifier = if_(var("bar"), vec![stmt], ast::ModifierForm::Modifier);
+        assert_eq!(emit(&modifier), "# lead\nfoo if bar # trail");
+    }
+
+    #[test]
+    fn modifier_in_value_is_parenthesized() {
+        let value = || if_(var("c"), vec![int(1)], ast::ModifierForm::Auto);
+        assert_eq!(emit(&assign("x", value())), "x = (1 if c)");
+        assert_eq!(
+            emit(&ast::Expression::Return(vec![
+                ast::ArrayInterpolation::Expression(value())
+            ])),
+            "return (1 if c)"
+        );
+        assert_eq!(emit(&call("foo", vec![value()])), "foo((1 if c))");
+        assert_eq!(emit(&send(value(), "to_s", vec![])), "(1 if c).to_s");
+        let endless = ast::EndlessMethod::new(
+            None,
+            ast::MethodName::parse("foo").unwrap(),
+            params(&[]),
+            Box::new(value()),
+        )
+        .unwrap();
+        assert_eq!(
+            emit(&ast::Expression::MethodDefinition(
+                ast::MethodDefinitionVariants::Endless(endless)
+            )),
+            "def foo = (1 if c)"
+        );
+        let block = if_(var("c"), vec![int(1)], ast::ModifierForm::Block);
+        assert_eq!(emit(&assign("x", block)), "x = if c\n  1\nend");
+    }
+
+    #[test]
+    fn condition_assigning_local_keeps_block_form() {
+        let assigned = if_(
+            assign("x", call("foo", vec![])),
+            vec![call("bar", vec![var("x")])],
+            ast::ModifierForm::Auto,
+        );
+        assert_eq!(emit(&assigned), "if x = foo\n  bar(x)\nend");
+    }
+}
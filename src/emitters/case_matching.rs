@@ -0,0 +1,137 @@
+use super::expression::Expression;
+use super::literals::array_interpolations;
+use super::{body, single_line, Emitter, MAX_LINE_WIDTH};
+use crate::ast;
+
+pub struct CaseMatching<'c>(pub(crate) &'c ast::CaseMatching);
+
+impl<'c> Emitter for CaseMatching<'c> {
+    fn emit(&self) -> String {
+        format!(
+            "case{}\n{}{}end",
+            self.0
+                .condition
+                .as_ref()
+                .map_or(String::new(), |cond| format!(
+                    " {}",
+                    Expression(cond).emit()
+                )),
+            self.0
+                .when
+                .iter()
+                .map(|wd| When(wd).emit())
+                .collect::<Vec<String>>()
+                .join(""),
+            self.0
+                .default
+                .as_ref()
+                .map_or(String::new(), |stmts| format!("else\n{}", body(&stmts.0)))
+        )
+    }
+}
+
+pub struct When<'w>(pub(crate) &'w ast::WhenDefinitionVariants);
+
+impl<'w> Emitter for When<'w> {
+    fn emit(&self) -> String {
+        let conditions = array_interpolations(&self.0.conditions);
+        match self.0.style {
+            ast::WhenStyle::Then => {
+                if let Some(stmt) = single_line(&self.0.iftrue.0) {
+                    let line = format!("when {} then {}", conditions, stmt);
+                    if line.len() <= MAX_LINE_WIDTH {
+                        return format!("{}\n", line);
+                    }
+                }
+                format!("when {} then\n{}", conditions, body(&self.0.iftrue.0))
+            }
+            ast::WhenStyle::Newline => format!("when {}\n{}", conditions, body(&self.0.iftrue.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn when(
+        conditions: Vec<ast::ArrayInterpolation>,
+        expressions: Vec<ast::Expression>,
+        style: ast::WhenStyle,
+    ) -> ast::WhenDefinitionVariants {
+        ast::WhenDefinitionVariants {
+            conditions,
+            iftrue: body(expressions),
+            style,
+        }
+    }
+
+    fn case(when: Vec<ast::WhenDefinitionVariants>, default: Option<ast::Body>) -> ast::Expression {
+        ast::Expression::CaseMatching(ast::CaseMatching {
+            condition: Some(Box::new(var("x"))),
+            when,
+            default,
+        })
+    }
+
+    #[test]
+    fn multiple_conditions() {
+        let others = ast::ArrayInterpolation::Splat(ast::ArrayExpression::Access(
+            ast::AccessVariants::Constant(ast::ConstantVariants::Unscoped(ast::Constant(
+                "OTHERS".to_string(),
+            ))),
+        ));
+        let conditions = vec![
+            ast::ArrayInterpolation::Expression(symbol("a")),
+            ast::ArrayInterpolation::Expression(symbol("b")),
+            others,
+        ];
+        let case = case(
+            vec![when(conditions, vec![int(1)], ast::WhenStyle::Then)],
+            Some(body(vec![int(2)])),
+        );
+        assert_eq!(
+            emit(&case),
+            "case x\nwhen :a, :b, *OTHERS then 1\nelse\n  2\nend"
+        );
+    }
+
+    #[test]
+    fn when_styles() {
+        let condition = || vec![ast::ArrayInterpolation::Expression(int(1))];
+        let case = case(
+            vec![
+                when(condition(), vec![var("a")], ast::WhenStyle::Newline),
+                when(condition(), vec![var("a"), var("b")], ast::WhenStyle::Then),
+                when(
+                    condition(),
+                    vec![var(&"x".repeat(80))],
+                    ast::WhenStyle::Then,
+                ),
+            ],
+            None,
+        );
+        assert_eq!(
+            emit(&case),
+            format!(
+                "case x\nwhen 1\n  a\nwhen 1 then\n  a\n  b\nwhen 1 then\n  {}\nend",
+                "x".repeat(80)
+            )
+        );
+    }
+
+    #[test]
+    fn case_without_condition() {
+        let case = ast::Expression::CaseMatching(ast::CaseMatching {
+            condition: None,
+            when: vec![when(
+                vec![ast::ArrayInterpolation::Expression(var("a"))],
+                vec![int(1)],
+                ast::WhenStyle::Then,
+            )],
+            default: None,
+        });
+        assert_eq!(emit(&case), "case\nwhen a then 1\nend");
+    }
+}
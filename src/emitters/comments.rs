@@ -0,0 +1,85 @@
+use super::expression::Expression;
+use super::{bare_statement, indent, placed, Emitter};
+use crate::ast;
+
+/// Emit expression as a statement preceded by its leading comments
+/// and followed by its trailing comment.
+pub(crate) fn commented(comments: &ast::Comments, exp: &ast::Expression) -> String {
+    around(comments, &bare_statement(exp))
+}
+
+/// Emit expression with its comments in expression position, e.g. an argument,
+/// in parentheses so the code following it isn't taken as a part of the comment.
+pub(crate) fn parenthesized(comments: &ast::Comments, exp: &ast::Expression) -> String {
+    if comments.is_empty() {
+        return Expression(exp).emit();
+    }
+    format!("(\n{})", indent(&commented(comments, exp)))
+}
+
+/// Wrap already emitted statement with comments, e.g. a statement in modifier form.
+pub(crate) fn around(comments: &ast::Comments, stmt: &str) -> String {
+    let mut code = comments
+        .leading
+        .iter()
+        .map(|c| format!("{}\n", comment(c)))
+        .collect::<String>();
+    code.push_str(stmt);
+    if let Some(text) = &comments.trailing {
+        code.push_str(&format!(" #{}", text));
+    }
+    code
+}
+
+/// Emit line comment or embedded document, embedded document
+/// has to start at the beginning of line so it's never indented.
+pub(crate) fn comment(c: &ast::CommentVariants) -> String {
+    match c {
+        ast::CommentVariants::Line(text) => format!("#{}", text),
+        ast::CommentVariants::Block(text) if text.is_empty() || text.ends_with('\n') => {
+            placed(&format!("=begin\n{}=end", text))
+        }
+        ast::CommentVariants::Block(text) => placed(&format!("=begin\n{}\n=end", text)),
+    }
+}
+
+/// Emit magic comment, e.g. `# frozen_string_literal: true`.
+pub(crate) fn magic_comment(mc: &ast::MagicComment) -> String {
+    match mc {
+        ast::MagicComment::Encoding(encoding) => format!("# encoding: {}", encoding),
+        ast::MagicComment::FrozenStringLiteral(frozen) => {
+            format!("# frozen_string_literal: {}", frozen)
+        }
+        ast::MagicComment::WarnIndent(warn) => format!("# warn_indent: {}", warn),
+        ast::MagicComment::ShareableConstantValue(value) => format!(
+            "# shareable_constant_value: {}",
+            match value {
+                ast::ShareableConstantValue::None => "none",
+                ast::ShareableConstantValue::Literal => "literal",
+                ast::ShareableConstantValue::ExperimentalEverything => "experimental_everything",
+                ast::ShareableConstantValue::ExperimentalCopy => "experimental_copy",
+            }
+        ),
+        ast::MagicComment::Typed(sigil) => format!(
+            "# typed: {}",
+            match sigil {
+                ast::TypedSigil::Ignore => "ignore",
+                ast::TypedSigil::False => "false",
+                ast::TypedSigil::True => "true",
+                ast::TypedSigil::Strict => "strict",
+                ast::TypedSigil::Strong => "strong",
+            }
+        ),
+    }
+}
+
+/// Position of magic comment in canonical order, following `ast::MagicComment` variants.
+pub(crate) fn magic_comment_order(mc: &ast::MagicComment) -> u8 {
+    match mc {
+        ast::MagicComment::Encoding(_) => 0,
+        ast::MagicComment::FrozenStringLiteral(_) => 1,
+        ast::MagicComment::WarnIndent(_) => 2,
+        ast::MagicComment::ShareableConstantValue(_) => 3,
+        ast::MagicComment::Typed(_) => 4,
+    }
+}
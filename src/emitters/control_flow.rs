@@ -0,0 +1,68 @@
+use super::literals::array_interpolations;
+use crate::ast;
+
+/// Emit `return`, `break` or `next` along with its values if any.
+pub(crate) fn jump(keyword: &str, values: &[ast::ArrayInterpolation]) -> String {
+    if values.is_empty() {
+        return keyword.to_string();
+    }
+    format!("{} {}", keyword, array_interpolations(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn values(expressions: Vec<ast::Expression>) -> Vec<ast::ArrayInterpolation> {
+        expressions
+            .into_iter()
+            .map(ast::ArrayInterpolation::Expression)
+            .collect()
+    }
+
+    #[test]
+    fn jumps() {
+        assert_eq!(emit(&ast::Expression::Return(vec![])), "return");
+        assert_eq!(
+            emit(&ast::Expression::Break(values(vec![int(1)]))),
+            "break 1"
+        );
+        assert_eq!(
+            emit(&ast::Expression::Next(values(vec![var("a"), var("b")]))),
+            "next a, b"
+        );
+        let splat = ast::ArrayInterpolation::Splat(ast::ArrayExpression::Access(
+            ast::AccessVariants::LocalVariable(ast::Variable("b".to_string())),
+        ));
+        let mut multiple = values(vec![var("a")]);
+        multiple.push(splat);
+        assert_eq!(emit(&ast::Expression::Return(multiple)), "return a, *b");
+        assert_eq!(emit(&ast::Expression::Redo), "redo");
+        assert_eq!(emit(&ast::Expression::Retry), "retry");
+    }
+
+    #[test]
+    fn next_in_block() {
+        let skip = ast::Expression::Branching(ast::BranchingVariants::If(
+            ast::BranchingIfVariants::WithoutElse(ast::BranchingIf {
+                condition: Box::new(send(var("x"), "nil?", vec![])),
+                iftrue: body(vec![ast::Expression::Next(vec![])]),
+                form: ast::ModifierForm::Auto,
+            }),
+        ));
+        let block = ast::Block {
+            params: ast::BlockParameters::explicit(params(&["x"]), vec![], false).unwrap(),
+            body: body(vec![skip]),
+            style: ast::BlockStyle::Auto,
+        };
+        let each = ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(
+            Box::new(var("list")),
+            method(
+                "each",
+                ast::SendMethodArgument(vec![], Some(ast::BlockArgument::BeginBlock(block))),
+            ),
+        ));
+        assert_eq!(emit(&each), "list.each { |x| next if x.nil? }");
+    }
+}
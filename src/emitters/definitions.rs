@@ -0,0 +1,556 @@
+use super::access::{constant_variants, global_variables};
+use super::arguments::FormalArgument;
+use super::expression::{element, primary, Expression};
+use super::{definition_body, Emitter};
+use crate::ast;
+
+pub struct MethodName<'m>(pub(crate) &'m ast::MethodName);
+
+impl<'m> Emitter for MethodName<'m> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::MethodName::Plain(v) => v.0.clone(),
+            ast::MethodName::Predicate(v) => format!("{}?", v.0),
+            ast::MethodName::Bang(v) => format!("{}!", v.0),
+            ast::MethodName::Setter(v) => format!("{}=", v.0),
+            ast::MethodName::Operator(op) => op.spelling().to_string(),
+            ast::MethodName::Index => "[]".to_string(),
+            ast::MethodName::IndexSetter => "[]=".to_string(),
+        }
+    }
+}
+
+pub struct MethodDefinition<'m>(pub(crate) &'m ast::MethodDefinitionVariants);
+
+impl<'m> Emitter for MethodDefinition<'m> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::MethodDefinitionVariants::Instance(im) => format!(
+                "def {}\n{}end",
+                signature(None, &im.name, &im.args),
+                definition_body(&im.body.0)
+            ),
+            ast::MethodDefinitionVariants::Singleton(sm) => format!(
+                "def {}\n{}end",
+                signature(Some(&sm.receiver), &sm.name, &sm.args),
+                definition_body(&sm.body.0)
+            ),
+            ast::MethodDefinitionVariants::Endless(em) => format!(
+                "def {} = {}",
+                signature(em.receiver.as_deref(), &em.name, &em.args),
+                element(&em.expression)
+            ),
+        }
+    }
+}
+
+fn signature(
+    recv: Option<&ast::Expression>,
+    name: &ast::MethodName,
+    args: &ast::FormalArgument,
+) -> String {
+    let args = FormalArgument(args).emit();
+    format!(
+        "{}{}{}",
+        recv.map_or(String::new(), |recv| format!("{}.", receiver(recv))),
+        MethodName(name).emit(),
+        if args.is_empty() {
+            args
+        } else {
+            format!("({})", args)
+        }
+    )
+}
+
+pub struct ClassDefinition<'c>(pub(crate) &'c ast::ClassDefinitionVariants);
+
+impl<'c> Emitter for ClassDefinition<'c> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::ClassDefinitionVariants::Class(cd) => format!(
+                "class {}{}\n{}end",
+                constant_variants(&cd.name),
+                cd.parent
+                    .as_ref()
+                    .map_or(String::new(), |parent| format!(" < {}", primary(parent))),
+                definition_body(&cd.body.0)
+            ),
+            ast::ClassDefinitionVariants::Singleton(scd) => format!(
+                "class << {}\n{}end",
+                receiver(&scd.receiver),
+                definition_body(&scd.body.0)
+            ),
+        }
+    }
+}
+
+/// Receiver of singleton definitions, anything other than
+/// self, variables and unscoped constants need to be parenthesized,
+/// e.g. `def (expr).foo`.
+fn receiver(exp: &ast::Expression) -> String {
+    match exp {
+        ast::Expression::Access(ast::AccessVariants::Constant(
+            ast::ConstantVariants::Unscoped(_),
+        ))
+        | ast::Expression::Access(ast::AccessVariants::_Self)
+        | ast::Expression::Access(ast::AccessVariants::LocalVariable(_))
+        | ast::Expression::Access(ast::AccessVariants::InstanceVariable(_))
+        | ast::Expression::Access(ast::AccessVariants::ClassVariable(_))
+        | ast::Expression::Access(ast::AccessVariants::GlobalVariable(_)) => Expression(exp).emit(),
+        _ => format!("({})", Expression(exp).emit()),
+    }
+}
+
+pub struct ModuleDefinition<'m>(pub(crate) &'m ast::ModuleDefinition);
+
+impl<'m> Emitter for ModuleDefinition<'m> {
+    fn emit(&self) -> String {
+        format!(
+            "module {}\n{}end",
+            constant_variants(&self.0.name),
+            definition_body(&self.0.body.0)
+        )
+    }
+}
+
+pub struct Aliasing<'a>(pub(crate) &'a ast::AliasingVariants);
+
+impl<'a> Emitter for Aliasing<'a> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::AliasingVariants::Method(am) => {
+                let name = |name| match am.style {
+                    ast::AliasStyle::Bareword => MethodName(name).emit(),
+                    ast::AliasStyle::Symbol => format!(":{}", MethodName(name).emit()),
+                };
+                format!("alias {} {}", name(&am.newname), name(&am.oldname))
+            }
+            ast::AliasingVariants::GlobalVariable(av) => format!(
+                "alias {} {}",
+                global_variables(&av.newname),
+                global_variables(&av.oldname)
+            ),
+        }
+    }
+}
+
+pub struct MethodUndefinition<'u>(pub(crate) &'u ast::MethodUndefinition);
+
+impl<'u> Emitter for MethodUndefinition<'u> {
+    fn emit(&self) -> String {
+        let ast::MethodUndefinition(names) = self.0;
+        format!(
+            "undef {}",
+            names
+                .iter()
+                .map(|name| MethodName(name).emit())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    #[test]
+    fn method_names() {
+        assert_eq!(
+            emit(&def("[]=", params(&["k", "v"]), vec![])),
+            "def []=(k, v)\nend"
+        );
+        assert_eq!(emit(&def("==", params(&["o"]), vec![])), "def ==(o)\nend");
+        assert_eq!(emit(&def("<=>", params(&["o"]), vec![])), "def <=>(o)\nend");
+        assert_eq!(emit(&def("+@", params(&[]), vec![])), "def +@\nend");
+        assert_eq!(emit(&def("~@", params(&[]), vec![])), "def ~\nend");
+        assert_eq!(
+            emit(&def("name=", params(&["v"]), vec![])),
+            "def name=(v)\nend"
+        );
+        assert_eq!(
+            emit(&def("valid?", params(&[]), vec![var("x")])),
+            "def valid?\n  x\nend"
+        );
+        assert_eq!(emit(&def("save!", params(&[]), vec![])), "def save!\nend");
+    }
+
+    fn singleton_def(receiver: ast::Expression) -> ast::Expression {
+        ast::Expression::MethodDefinition(ast::MethodDefinitionVariants::Singleton(
+            ast::SingletonMethod {
+                receiver: Box::new(receiver),
+                name: ast::MethodName::parse("foo").unwrap(),
+                args: params(&["a"]),
+                body: body(vec![]),
+            },
+        ))
+    }
+
+    fn singleton_class(receiver: ast::Expression) -> ast::Expression {
+        ast::Expression::ClassDefinition(ast::ClassDefinitionVariants::Singleton(
+            ast::SingletonClassDefinition {
+                receiver: Box::new(receiver),
+                body: body(vec![var("x")]),
+            },
+        ))
+    }
+
+    #[test]
+    fn singleton_receivers() {
+        let this = ast::Expression::Access(ast::AccessVariants::_Self);
+        assert_eq!(emit(&singleton_def(this)), "def self.foo(a)\nend");
+        assert_eq!(emit(&singleton_def(var("obj"))), "def obj.foo(a)\nend");
+        assert_eq!(emit(&singleton_def(constant("Foo"))), "def Foo.foo(a)\nend");
+        assert_eq!(
+            emit(&singleton_def(send(var("a"), "b", vec![]))),
+            "def (a.b).foo(a)\nend"
+        );
+        let this = ast::Expression::Access(ast::AccessVariants::_Self);
+        assert_eq!(emit(&singleton_class(this)), "class << self\n  x\nend");
+        assert_eq!(emit(&singleton_class(var("obj"))), "class << obj\n  x\nend");
+        assert_eq!(
+            emit(&singleton_class(send(constant("Foo"), "new", vec![]))),
+            "class << (Foo.new)\n  x\nend"
+        );
+    }
+
+    fn class(parent: ast::Expression) -> ast::Expression {
+        ast::Expression::ClassDefinition(ast::ClassDefinitionVariants::Class(
+            ast::ClassDefinition {
+                name: ast::ConstantVariants::Unscoped(ast::Constant("Foo".to_string())),
+                parent: Some(Box::new(parent)),
+                body: body(vec![]),
+            },
+        ))
+    }
+
+    #[test]
+    fn superclasses() {
+        assert_eq!(emit(&class(constant("Base"))), "class Foo < Base\nend");
+        assert_eq!(
+            emit(&class(send(
+                constant("Struct"),
+                "new",
+                vec![symbol("x"), symbol("y")]
+            ))),
+            "class Foo < Struct.new(:x, :y)\nend"
+        );
+        assert_eq!(
+            emit(&class(send(constant("Base"), "[]", vec![symbol("v2")]))),
+            "class Foo < Base[:v2]\nend"
+        );
+        let migration = ast::Expression::Access(ast::AccessVariants::Constant(
+            ast::ConstantVariants::Scoped(vec![
+                ast::Constant("ActiveRecord".to_string()),
+                ast::Constant("Migration".to_string()),
+            ]),
+        ));
+        let version = ast::Expression::Literal(ast::ValueVariants::Float(ast::FloatLiteral(7.1)));
+        assert_eq!(
+            emit(&class(send(migration, "[]", vec![version]))),
+            "class Foo < ActiveRecord::Migration[7.1]\nend"
+        );
+    }
+
+    #[test]
+    fn range_receiver_is_parenthesized() {
+        let range = ast::Expression::Literal(ast::ValueVariants::Range(
+            ast::RangeLiteral::Exclusive(ast::IntegerLiteral(1), Some(ast::IntegerLiteral(2))),
+        ));
+        assert_eq!(emit(&singleton_def(range)), "def (1...2).foo(a)\nend");
+    }
+
+    fn endless(name: &str, args: ast::FormalArgument, body: ast::Expression) -> ast::Expression {
+        ast::Expression::MethodDefinition(ast::MethodDefinitionVariants::Endless(
+            ast::EndlessMethod::new(
+                None,
+                ast::MethodName::parse(name).unwrap(),
+                args,
+                Box::new(body),
+            )
+            .unwrap(),
+        ))
+    }
+
+    fn logical(
+        variant: fn(Box<ast::Expression>, Box<ast::Expression>) -> ast::LogicalOperationVariants,
+    ) -> ast::Expression {
+        ast::Expression::LogicalOperation(variant(Box::new(var("a")), Box::new(var("b"))))
+    }
+
+    #[test]
+    fn endless_methods() {
+        let square = binary(var("x"), ast::BinaryOperator::Multiply, var("x"));
+        assert_eq!(
+            emit(&endless("square", params(&["x"]), square)),
+            "def square(x) = x * x"
+        );
+        assert_eq!(
+            emit(&endless("valid?", params(&[]), var("x"))),
+            "def valid? = x"
+        );
+        let this = ast::Expression::Access(ast::AccessVariants::_Self);
+        let singleton = ast::EndlessMethod::new(
+            Some(Box::new(this)),
+            ast::MethodName::parse("foo").unwrap(),
+            params(&[]),
+            Box::new(int(1)),
+        )
+        .unwrap();
+        assert_eq!(
+            emit(&ast::Expression::MethodDefinition(
+                ast::MethodDefinitionVariants::Endless(singleton)
+            )),
+            "def self.foo = 1"
+        );
+    }
+
+    #[test]
+    fn endless_method_body_is_an_argument() {
+        let and = logical(ast::LogicalOperationVariants::LowerPrecedenceAnd);
+        assert_eq!(
+            emit(&endless("foo", params(&[]), and)),
+            "def foo = (a and b)"
+        );
+        let or = logical(ast::LogicalOperationVariants::LowerPrecedenceOr);
+        assert_eq!(emit(&endless("foo", params(&[]), or)), "def foo = (a or b)");
+        let and = logical(ast::LogicalOperationVariants::And);
+        assert_eq!(emit(&endless("foo", params(&[]), and)), "def foo = a && b");
+        let rescue = ast::Expression::ExceptionHandling(
+            ast::ExceptionHandlingVariants::InlineRescue(Box::new(var("a")), Box::new(nil())),
+        );
+        assert_eq!(
+            emit(&endless("foo", params(&[]), rescue)),
+            "def foo = (a rescue nil)"
+        );
+    }
+
+    #[test]
+    fn endless_setters_are_rejected() {
+        for name in &["foo=", "[]="] {
+            let setter = ast::EndlessMethod::new(
+                None,
+                ast::MethodName::parse(name).unwrap(),
+                params(&["v"]),
+                Box::new(var("v")),
+            );
+            assert!(setter.is_none(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn argument_forwarding() {
+        let forward = ast::FormalArgument(vec![ast::Parameter::ForwardArguments]);
+        let args = ast::SendMethodArgument::new(
+            vec![ast::ArgumentVariants::ForwardArguments],
+            None,
+            Some(&forward),
+        )
+        .unwrap();
+        let call =
+            ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method("bar", args)));
+        assert_eq!(
+            emit(&def("foo", forward, vec![call])),
+            "def foo(...)\n  bar(...)\nend"
+        );
+        let anonymous = ast::FormalArgument(vec![
+            ast::Parameter::Rest(None),
+            ast::Parameter::KeywordRest(None),
+            ast::Parameter::Block(None),
+        ]);
+        let args = ast::SendMethodArgument::new(
+            vec![
+                ast::ArgumentVariants::AnonymousSplat,
+                ast::ArgumentVariants::AnonymousKeywordSplat,
+            ],
+            Some(ast::BlockArgument::AnonymousPass),
+            Some(&anonymous),
+        )
+        .unwrap();
+        let call =
+            ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method("bar", args)));
+        assert_eq!(
+            emit(&def("foo", anonymous, vec![call])),
+            "def foo(*, **, &)\n  bar(*, **, &)\nend"
+        );
+    }
+
+    #[test]
+    fn argument_forwarding_requires_parameters() {
+        let forward = ast::FormalArgument(vec![ast::Parameter::ForwardArguments]);
+        let new = |args, block, params| ast::SendMethodArgument::new(args, block, params).is_some();
+        assert!(!new(
+            vec![ast::ArgumentVariants::ForwardArguments],
+            None,
+            None
+        ));
+        assert!(!new(
+            vec![ast::ArgumentVariants::AnonymousSplat],
+            None,
+            Some(&forward)
+        ));
+        assert!(!new(
+            vec![ast::ArgumentVariants::AnonymousKeywordSplat],
+            None,
+            Some(&forward)
+        ));
+        assert!(!new(
+            vec![],
+            Some(ast::BlockArgument::AnonymousPass),
+            Some(&forward)
+        ));
+        assert!(!new(
+            vec![
+                ast::ArgumentVariants::ForwardArguments,
+                ast::ArgumentVariants::Expression(int(1))
+            ],
+            None,
+            Some(&forward)
+        ));
+        let block = ast::BlockArgument::Pass(ast::ProcAsArgumentVariants::Variable(ast::Variable(
+            "blk".to_string(),
+        )));
+        assert!(!new(
+            vec![ast::ArgumentVariants::ForwardArguments],
+            Some(block),
+            Some(&forward)
+        ));
+        assert!(new(
+            vec![
+                ast::ArgumentVariants::Expression(int(1)),
+                ast::ArgumentVariants::ForwardArguments
+            ],
+            None,
+            Some(&forward)
+        ));
+    }
+
+    #[test]
+    fn minimum_versions() {
+        let version = |params| ast::FormalArgument(params).minimum_version();
+        assert_eq!(version(vec![]), None);
+        assert_eq!(
+            version(vec![ast::Parameter::ForwardArguments]),
+            Some(ast::RubyVersion(2, 7))
+        );
+        assert_eq!(
+            version(vec![
+                ast::Parameter::Required(ast::Variable("a".to_string())),
+                ast::Parameter::ForwardArguments
+            ]),
+            Some(ast::RubyVersion(3, 0))
+        );
+        assert_eq!(
+            version(vec![ast::Parameter::Block(None)]),
+            Some(ast::RubyVersion(3, 1))
+        );
+        let anonymous = ast::FormalArgument(vec![ast::Parameter::Rest(None)]);
+        let args = ast::SendMethodArgument::new(
+            vec![ast::ArgumentVariants::AnonymousSplat],
+            None,
+            Some(&anonymous),
+        )
+        .unwrap();
+        assert_eq!(args.minimum_version(), Some(ast::RubyVersion(3, 2)));
+        let endless = ast::MethodDefinitionVariants::Endless(
+            ast::EndlessMethod::new(
+                None,
+                ast::MethodName::parse("foo").unwrap(),
+                params(&[]),
+                Box::new(int(1)),
+            )
+            .unwrap(),
+        );
+        assert_eq!(endless.minimum_version(), Some(ast::RubyVersion(3, 0)));
+        let instance = ast::MethodDefinitionVariants::Instance(ast::InstanceMethod {
+            name: ast::MethodName::parse("foo").unwrap(),
+            args: params(&["a"]),
+            body: body(vec![]),
+        });
+        assert_eq!(instance.minimum_version(), None);
+    }
+
+    #[test]
+    fn method_name_parse_rejects() {
+        for name in &["", "?", "a b", "1a", "foo?=", "[]?", "+=", "&&", "@foo"] {
+            assert!(ast::MethodName::parse(name).is_none(), "{}", name);
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<ast::MethodName> {
+        names
+            .iter()
+            .map(|name| ast::MethodName::parse(name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn method_aliases() {
+        let alias = |style| {
+            emit(&ast::Expression::Aliasing(ast::AliasingVariants::Method(
+                ast::AliasingMethod {
+                    oldname: ast::MethodName::parse("inspect").unwrap(),
+                    newname: ast::MethodName::parse("to_s").unwrap(),
+                    style,
+                },
+            )))
+        };
+        assert_eq!(alias(ast::AliasStyle::Bareword), "alias to_s inspect");
+        assert_eq!(alias(ast::AliasStyle::Symbol), "alias :to_s :inspect");
+    }
+
+    #[test]
+    fn alias_method_sugar() {
+        let alias_method = |receiver: Option<ast::Expression>, args| {
+            let method = method("alias_method", super::super::testing::args(args));
+            let send = match receiver {
+                Some(receiver) => ast::SendMethodVariants::WithReceiver(Box::new(receiver), method),
+                None => ast::SendMethodVariants::Singleton(method),
+            };
+            ast::AliasingMethod::from_alias_method(&send).map(|am| {
+                emit(&ast::Expression::Aliasing(ast::AliasingVariants::Method(
+                    am,
+                )))
+            })
+        };
+        assert_eq!(
+            alias_method(None, vec![symbol("eql?"), symbol("==")]),
+            Some("alias :eql? :==".to_string())
+        );
+        let this = ast::Expression::Access(ast::AccessVariants::_Self);
+        assert_eq!(
+            alias_method(Some(this), vec![symbol("a"), symbol("b")]),
+            Some("alias :a :b".to_string())
+        );
+        assert_eq!(
+            alias_method(Some(var("obj")), vec![symbol("a"), symbol("b")]),
+            None
+        );
+        assert_eq!(alias_method(None, vec![symbol("a"), var("b")]), None);
+        assert_eq!(alias_method(None, vec![symbol("a")]), None);
+    }
+
+    #[test]
+    fn global_variable_aliases() {
+        let global = |name| ast::GlobalVariable::parse(name).unwrap();
+        let alias = ast::AliasingVariable::new(global("$new"), global("$old")).unwrap();
+        assert_eq!(
+            emit(&ast::Expression::Aliasing(
+                ast::AliasingVariants::GlobalVariable(alias)
+            )),
+            "alias $new $old"
+        );
+        assert!(ast::AliasingVariable::new(global("$new"), global("$1")).is_none());
+    }
+
+    #[test]
+    fn method_undefinition() {
+        let undef = ast::MethodUndefinition::new(names(&["foo", "bar=", "<=>"])).unwrap();
+        assert_eq!(
+            emit(&ast::Expression::MethodUndefinition(undef)),
+            "undef foo, bar=, <=>"
+        );
+        assert!(ast::MethodUndefinition::new(vec![]).is_none());
+    }
+}
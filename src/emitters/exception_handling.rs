@@ -0,0 +1,166 @@
+use super::access::Access;
+use super::expression::primary;
+use super::literals::array_interpolations;
+use super::{bare_statement, body, Emitter};
+use crate::ast;
+
+pub struct ExceptionHandling<'e>(pub(crate) &'e ast::ExceptionHandlingVariants);
+
+impl<'e> Emitter for ExceptionHandling<'e> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::ExceptionHandlingVariants::InlineRescue(exp, rescue) => {
+                format!("{} rescue {}", bare_statement(exp), primary(rescue))
+            }
+            ast::ExceptionHandlingVariants::Begin(eh)
+            | ast::ExceptionHandlingVariants::Implicit(eh) => {
+                format!("begin\n{}end", clauses(eh))
+            }
+        }
+    }
+}
+
+/// Emit body along with its rescue, else and ensure clauses,
+/// without `begin` and `end`.
+pub(crate) fn clauses(eh: &ast::ExceptionHandling) -> String {
+    format!(
+        "{}{}{}{}",
+        body(&eh.body.0),
+        eh.rescues
+            .iter()
+            .map(rescue_clause)
+            .collect::<Vec<String>>()
+            .join(""),
+        eh.otherwise
+            .as_ref()
+            .map_or(String::new(), |stmts| format!("else\n{}", body(&stmts.0))),
+        eh.ensure
+            .as_ref()
+            .map_or(String::new(), |stmts| format!("ensure\n{}", body(&stmts.0)))
+    )
+}
+
+fn rescue_clause(rb: &ast::RescueBody) -> String {
+    let exceptions = array_interpolations(&rb.exceptions);
+    format!(
+        "rescue{}{}\n{}",
+        if exceptions.is_empty() {
+            exceptions
+        } else {
+            format!(" {}", exceptions)
+        },
+        rb.assignment
+            .as_ref()
+            .map_or(String::new(), |acc| format!(" => {}", Access(acc).emit())),
+        body(&rb.body.0)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn rescue(
+        exceptions: Vec<ast::ArrayInterpolation>,
+        assignment: Option<&str>,
+        expressions: Vec<ast::Expression>,
+    ) -> ast::RescueBody {
+        ast::RescueBody {
+            exceptions,
+            assignment: assignment
+                .map(|name| ast::AccessVariants::LocalVariable(ast::Variable(name.to_string()))),
+            body: body(expressions),
+        }
+    }
+
+    fn handling() -> ast::ExceptionHandling {
+        let errors = ast::ArrayInterpolation::Splat(ast::ArrayExpression::Access(
+            ast::AccessVariants::Constant(ast::ConstantVariants::Unscoped(ast::Constant(
+                "NETWORK_ERRORS".to_string(),
+            ))),
+        ));
+        ast::ExceptionHandling::new(
+            body(vec![call("fetch", vec![])]),
+            vec![
+                rescue(
+                    vec![
+                        ast::ArrayInterpolation::Expression(constant("Timeout")),
+                        errors,
+                    ],
+                    Some("e"),
+                    vec![call("log", vec![var("e")]), ast::Expression::Retry],
+                ),
+                rescue(vec![], None, vec![nil()]),
+            ],
+            Some(body(vec![call("done", vec![])])),
+            Some(body(vec![call("close", vec![])])),
+        )
+        .unwrap()
+    }
+
+    const CLAUSES: &str = concat!(
+        "  fetch\n",
+        "rescue Timeout, *NETWORK_ERRORS => e\n",
+        "  log(e)\n",
+        "  retry\n",
+        "rescue\n",
+        "  nil\n",
+        "else\n",
+        "  done\n",
+        "ensure\n",
+        "  close\n",
+    );
+
+    #[test]
+    fn begin_rescue_else_ensure() {
+        let begin =
+            ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Begin(handling()));
+        assert_eq!(emit(&begin), format!("begin\n{}end", CLAUSES));
+    }
+
+    #[test]
+    fn implicit_clauses() {
+        let implicit = || {
+            ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Implicit(handling()))
+        };
+        assert_eq!(
+            emit(&def("foo", params(&[]), vec![implicit()])),
+            format!("def foo\n{}end", CLAUSES)
+        );
+        let block = ast::Block {
+            params: ast::BlockParameters::Implicit,
+            body: body(vec![implicit()]),
+            style: ast::BlockStyle::Braces,
+        };
+        let call = ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method(
+            "foo",
+            ast::SendMethodArgument(vec![], Some(ast::BlockArgument::BeginBlock(block))),
+        )));
+        assert_eq!(emit(&call), format!("foo do\n{}end", CLAUSES));
+        // Not the only statement of the body.
+        let def = def("foo", params(&[]), vec![var("x"), implicit()]);
+        assert_eq!(
+            emit(&def),
+            format!(
+                "def foo\n  x\n  begin\n{}  end\nend",
+                super::super::indent(CLAUSES)
+            )
+        );
+    }
+
+    #[test]
+    fn clauses_are_validated() {
+        let new = |rescues, otherwise, ensure| {
+            ast::ExceptionHandling::new(body(vec![]), rescues, otherwise, ensure).is_some()
+        };
+        assert!(!new(vec![], None, None));
+        assert!(!new(vec![], Some(body(vec![])), Some(body(vec![]))));
+        assert!(new(vec![], None, Some(body(vec![]))));
+        assert!(new(
+            vec![rescue(vec![], None, vec![])],
+            Some(body(vec![])),
+            None
+        ));
+    }
+}
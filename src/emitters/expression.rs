@@ -1,9 +1,200 @@
+use super::access::Access;
+use super::assignment::Assignment;
+use super::blocks::ProcExpression;
+use super::branching::{Branching, TernaryBranching};
+use super::case_matching::CaseMatching;
+use super::comments::parenthesized;
+use super::control_flow::jump;
+use super::definitions::{
+    Aliasing, ClassDefinition, MethodDefinition, MethodUndefinition, ModuleDefinition,
+};
+use super::exception_handling::ExceptionHandling;
+use super::literals::Literals;
+use super::loops::Loop;
+use super::operation::{special_block, FlipFlop, LogicalOperation, Operation};
+use super::send_method::SendMethod;
+use super::{bare_statement, modifier_form, Emitter};
 use crate::ast;
 
 pub struct Expression<'e>(pub(crate) &'e ast::Expression);
 
-impl<'e> super::Emitter for Expression<'e> {
+impl<'e> Emitter for Expression<'e> {
     fn emit(&self) -> String {
-        "".to_string()
+        match self.0 {
+            ast::Expression::Literal(l) => Literals(l).emit(),
+            ast::Expression::Access(a) => Access(a).emit(),
+            ast::Expression::ClassDefinition(cd) => ClassDefinition(cd).emit(),
+            ast::Expression::ModuleDefinition(md) => ModuleDefinition(md).emit(),
+            ast::Expression::MethodDefinition(md) => MethodDefinition(md).emit(),
+            ast::Expression::MethodUndefinition(mu) => MethodUndefinition(mu).emit(),
+            ast::Expression::Aliasing(a) => Aliasing(a).emit(),
+            ast::Expression::Assignment(asgn) => Assignment(asgn).emit(),
+            // Statement emits its comments around it, see `super::statement`.
+            ast::Expression::Commented(comments, exp) => parenthesized(comments, exp),
+            // Original source is only printed for statements, see `super::statement`.
+            ast::Expression::Verbatim(v) => Expression(&v.expression).emit(),
+            ast::Expression::SendMethod(sm) => SendMethod(sm).emit(),
+            ast::Expression::Proc(pexp) => ProcExpression(pexp).emit(),
+            ast::Expression::Branching(b) => Branching(b).emit(),
+            ast::Expression::TernaryBranching(tb) => TernaryBranching(tb).emit(),
+            ast::Expression::CaseMatching(cm) => CaseMatching(cm).emit(),
+            ast::Expression::Loop(l) => Loop(l).emit(),
+            ast::Expression::ExceptionHandling(eh) => ExceptionHandling(eh).emit(),
+            ast::Expression::Return(values) => jump("return", values),
+            ast::Expression::Break(values) => jump("break", values),
+            ast::Expression::Next(values) => jump("next", values),
+            ast::Expression::Redo => "redo".to_string(),
+            ast::Expression::Retry => "retry".to_string(),
+            ast::Expression::Operation(op) => Operation(op).emit(),
+            ast::Expression::LogicalOperation(lop) => LogicalOperation(lop).emit(),
+            ast::Expression::BEGINBlock(ast::BEGINBlock(stmts)) => special_block("BEGIN", stmts),
+            ast::Expression::ENDBlock(ast::ENDBlock(stmts)) => special_block("END", stmts),
+            ast::Expression::FlipFlop(ff) => FlipFlop(ff).emit(),
+        }
+    }
+}
+
+/// Emit expression so it can be used as a receiver or an operand,
+/// parenthesize it unless it's a primary expression.
+pub(crate) fn primary(exp: &ast::Expression) -> String {
+    match exp {
+        ast::Expression::Commented(comments, exp) if comments.is_empty() => primary(exp),
+        ast::Expression::Commented(comments, exp) => parenthesized(comments, exp),
+        ast::Expression::Verbatim(v) => primary(&v.expression),
+        // `1..2.foo` would call `foo` on `2`.
+        ast::Expression::Literal(ast::ValueVariants::Range(_)) => {
+            format!("({})", Expression(exp).emit())
+        }
+        ast::Expression::Literal(_)
+        | ast::Expression::Access(_)
+        | ast::Expression::Proc(_)
+        | ast::Expression::Operation(ast::OperationVariants::Paren(_)) => Expression(exp).emit(),
+        ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(_, sm))
+            if is_operator_sugar(&sm.name) =>
+        {
+            format!("({})", Expression(exp).emit())
+        }
+        ast::Expression::SendMethod(_) => Expression(exp).emit(),
+        _ => format!("({})", bare_statement(exp)),
+    }
+}
+
+/// Emit expression as an argument, an array element, a hash value,
+/// an assigned value or the body of an endless method. Statements which
+/// can't be used there are wrapped in parentheses, e.g. `foo((a, b = 1, 2))`
+/// where commas would be taken as separators, or `x = (a and b)`
+/// which would otherwise assign `a` only.
+pub(crate) fn element(exp: &ast::Expression) -> String {
+    if is_statement(exp) {
+        return format!("({})", Expression(exp).emit());
+    }
+    match modifier_form(exp) {
+        Some(line) => format!("({})", line),
+        None => Expression(exp).emit(),
+    }
+}
+
+/// Whether expression is only valid as a statement or in parentheses:
+/// multiple assignment, `and`, `or`, `not` and modifier `rescue`.
+/// Conditionals and loops in modifier form are also statements, see `modifier_form`.
+fn is_statement(exp: &ast::Expression) -> bool {
+    match exp {
+        ast::Expression::Commented(comments, exp) if comments.is_empty() => is_statement(exp),
+        ast::Expression::Verbatim(v) => is_statement(&v.expression),
+        ast::Expression::Assignment(ast::AssignmentVariants::MultipleAssignment(..))
+        | ast::Expression::LogicalOperation(ast::LogicalOperationVariants::LowerPrecedenceAnd(
+            ..,
+        ))
+        | ast::Expression::LogicalOperation(ast::LogicalOperationVariants::LowerPrecedenceOr(..))
+        | ast::Expression::Operation(ast::OperationVariants::Not(_))
+        | ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::InlineRescue(..)) => {
+            true
+        }
+        _ => false,
+    }
+}
+
+fn is_operator_sugar(name: &ast::MethodName) -> bool {
+    matches!(
+        name,
+        ast::MethodName::Operator(_) | ast::MethodName::IndexSetter | ast::MethodName::Setter(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn range(from: i64, to: Option<i64>) -> ast::Expression {
+        ast::Expression::Literal(ast::ValueVariants::Range(ast::RangeLiteral::Inclusive(
+            ast::IntegerLiteral(from),
+            to.map(ast::IntegerLiteral),
+        )))
+    }
+
+    #[test]
+    fn receivers() {
+        assert_eq!(
+            emit(&send(range(1, Some(2)), "to_a", vec![])),
+            "(1..2).to_a"
+        );
+        assert_eq!(emit(&send(range(1, None), "first", vec![])), "(1..).first");
+        assert_eq!(emit(&send(nil(), "to_a", vec![])), "nil.to_a");
+        assert_eq!(emit(&send(int(1), "succ", vec![])), "1.succ");
+        assert_eq!(
+            emit(&send(send(var("a"), "b", vec![]), "c", vec![])),
+            "a.b.c"
+        );
+        assert_eq!(
+            emit(&send(
+                binary(var("a"), ast::BinaryOperator::Add, int(1)),
+                "abs",
+                vec![]
+            )),
+            "(a + 1).abs"
+        );
+        assert_eq!(
+            emit(&send(send(var("a"), "+", vec![int(1)]), "abs", vec![])),
+            "(a + 1).abs"
+        );
+    }
+
+    #[test]
+    fn operands() {
+        let sum = binary(range(1, Some(2)), ast::BinaryOperator::Add, int(1));
+        assert_eq!(emit(&sum), "(1..2) + 1");
+    }
+
+    #[test]
+    fn statements_in_arguments_are_parenthesized() {
+        let rescue = || {
+            ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::InlineRescue(
+                Box::new(var("a")),
+                Box::new(var("b")),
+            ))
+        };
+        assert_eq!(emit(&rescue()), "a rescue b");
+        assert_eq!(emit(&call("foo", vec![rescue()])), "foo((a rescue b))");
+        assert_eq!(emit(&assign("x", rescue())), "x = (a rescue b)");
+        let and =
+            ast::Expression::LogicalOperation(ast::LogicalOperationVariants::LowerPrecedenceAnd(
+                Box::new(var("a")),
+                Box::new(var("b")),
+            ));
+        assert_eq!(emit(&assign("x", and)), "x = (a and b)");
+        let or =
+            ast::Expression::LogicalOperation(ast::LogicalOperationVariants::LowerPrecedenceOr(
+                Box::new(var("a")),
+                Box::new(var("b")),
+            ));
+        assert_eq!(
+            emit(&ast::Expression::Return(vec![
+                ast::ArrayInterpolation::Expression(or)
+            ])),
+            "return (a or b)"
+        );
+        let not = ast::Expression::Operation(ast::OperationVariants::Not(Box::new(var("a"))));
+        assert_eq!(emit(&call("foo", vec![not])), "foo((not a))");
     }
 }
@@ -1,4 +1,4 @@
-use super::Emitter;
+use super::{bare_statement, deferred, indent, Emitter, MAX_LINE_WIDTH, PLACED};
 use crate::ast;
 use regex::Regex;
 
@@ -12,10 +12,13 @@ impl<'l> Emitter for Literals<'l> {
             ast::ValueVariants::Float(f) => f.0.to_string(),
             ast::ValueVariants::Complex(c) => format!("{}i", c.0),
             ast::ValueVariants::Rational(r) => r.0.to_string(),
-            ast::ValueVariants::String(s) => format!("\"{}\"", StringVal(s).emit()),
-            ast::ValueVariants::Symbol(s) => format!(":{}", SymVal(s).emit()),
+            ast::ValueVariants::String(s, form) => encoded(s, string_literal(s, form)),
+            ast::ValueVariants::Symbol(s, form) => symbol_literal(s, form),
             ast::ValueVariants::HereDocument(hd) => HereDoc(hd).emit(),
-            ast::ValueVariants::ExecuteString(s) => format!("`{}`", StringVal(s).emit()),
+            ast::ValueVariants::ExecuteString(s, form) => match form {
+                ast::LiteralForm::Percent(pd) => percent_literal("x", s, pd, true),
+                _ => format!("`{}`", delimited(s, '`', true)),
+            },
             ast::ValueVariants::RegularExpression(rgx) => RegularExpression(rgx).emit(),
             ast::ValueVariants::Array(arr) => ArrayVal(arr).emit(),
             ast::ValueVariants::Hash(h) => HashVal(h).emit(),
@@ -28,11 +31,7 @@ pub struct Singleton<'s>(pub(crate) &'s ast::SingletonVariants);
 
 impl<'s> Emitter for Singleton<'s> {
     fn emit(&self) -> String {
-        match self.0 {
-            ast::SingletonVariants::False => "false".to_string(),
-            ast::SingletonVariants::True => "true".to_string(),
-            ast::SingletonVariants::Nil => "nil".to_string(),
-        }
+        self.0.keyword().to_string()
     }
 }
 
@@ -41,25 +40,271 @@ pub struct StringVal<'s>(pub(crate) &'s ast::StringLiteral);
 impl<'s> Emitter for StringVal<'s> {
     fn emit(&self) -> String {
         match self.0 {
-            ast::StringLiteral::Static(s) => *s,
-            ast::StringLiteral::WithInterpolation(v) => {
-                v.iter().fold(String::new(), |buff, exp| {
-                    format!("{}{}", buff, string_interpolate(exp))
-                })
+            ast::StringLiteral::Static(s) => s.clone(),
+            ast::StringLiteral::Bytes(bs) => escape_bytes(bs, |st| st.to_string()),
+            ast::StringLiteral::WithInterpolation(v) => interpolation(v, &mut |st| st.to_string()),
+        }
+    }
+}
+
+use super::expression::element;
+
+/// Emit content of interpolated string, symbol, regular expression
+/// or command, static parts are escaped with `text`.
+/// Instance, class and global variable is written in its short form, e.g. `#@ivar`,
+/// while the same variable in `begin` (`Paren`) is written as `#{@ivar}`,
+/// following how the parser tells them apart.
+/// Nested interpolated string parts are flattened.
+fn interpolation(parts: &[ast::Expression], text: &mut dyn FnMut(&str) -> String) -> String {
+    let mut code = String::new();
+    for (i, exp) in parts.iter().enumerate() {
+        match exp {
+            ast::Expression::Literal(ast::ValueVariants::String(
+                ast::StringLiteral::Static(st),
+                _,
+            )) => code.push_str(&text(st)),
+            ast::Expression::Literal(ast::ValueVariants::String(
+                ast::StringLiteral::Bytes(bs),
+                _,
+            )) => code.push_str(&escape_bytes(bs, &mut *text)),
+            ast::Expression::Literal(ast::ValueVariants::String(
+                ast::StringLiteral::WithInterpolation(v),
+                _,
+            )) => code.push_str(&interpolation(v, text)),
+            ast::Expression::Access(
+                acc @ (ast::AccessVariants::InstanceVariable(_)
+                | ast::AccessVariants::ClassVariable(_)
+                | ast::AccessVariants::GlobalVariable(_)),
+            ) if !continues_name(parts.get(i + 1)) => {
+                code.push_str(&format!("#{}", Access(acc).emit()))
             }
+            ast::Expression::Operation(ast::OperationVariants::Paren(stmts)) => {
+                code.push_str(&format!(
+                    "#{{{}}}",
+                    stmts
+                        .0
+                        .iter()
+                        .map(bare_statement)
+                        .collect::<Vec<String>>()
+                        .join("; ")
+                ))
+            }
+            _ => code.push_str(&format!("#{{{}}}", bare_statement(exp))),
         }
     }
+    code
+}
+
+/// Whether the next interpolation part would be read as the rest
+/// of a variable name in short form, e.g. `#@a` followed by `b`.
+fn continues_name(next: Option<&ast::Expression>) -> bool {
+    let first = match next {
+        Some(ast::Expression::Literal(ast::ValueVariants::String(
+            ast::StringLiteral::Static(st),
+            _,
+        ))) => st.chars().next(),
+        Some(ast::Expression::Literal(ast::ValueVariants::String(
+            ast::StringLiteral::Bytes(bs),
+            _,
+        ))) => bs.bytes.first().map(|byte| *byte as char),
+        Some(ast::Expression::Literal(ast::ValueVariants::String(
+            ast::StringLiteral::WithInterpolation(v),
+            _,
+        ))) => return continues_name(v.first()),
+        _ => None,
+    };
+    first.is_some_and(|c| c.is_alphanumeric() || c == '_' || !c.is_ascii())
 }
 
-use super::expression::Expression;
+/// Emit string literal in its form, e.g. `"str"`, `'str'`, `%q{str}` or `?a`.
+fn string_literal(s: &ast::StringLiteral, form: &ast::LiteralForm) -> String {
+    match (form, s) {
+        (ast::LiteralForm::SingleQuoted, ast::StringLiteral::Static(st)) => {
+            format!("'{}'", escape(st, &['\''], false))
+        }
+        (ast::LiteralForm::Percent(pd), _) if pd.interpolated || is_static(s) => percent_literal(
+            if pd.interpolated { "Q" } else { "q" },
+            s,
+            pd,
+            pd.interpolated,
+        ),
+        (ast::LiteralForm::Character, ast::StringLiteral::Static(st)) if is_character(st) => {
+            format!("?{}", escape(st, &[], false))
+        }
+        _ => format!("\"{}\"", delimited(s, '"', true)),
+    }
+}
 
-fn string_interpolate(exp: &ast::Expression) -> String {
-    match exp {
-        ast::Expression::Literal(l) => match l {
-            ast::ValueVariants::String(s) => StringVal(s).emit(),
-            _ => format!("#{{{}}}", Literals(l).emit()),
+/// Wrap emitted literal to keep the encoding of byte string other than UTF-8,
+/// since a literal follows the source encoding, e.g. `"\xFF".b`.
+/// The literal is copied rather than changed in place by `force_encoding`,
+/// since it may be frozen, e.g. by `# frozen_string_literal: true`.
+fn encoded(s: &ast::StringLiteral, literal: String) -> String {
+    match s {
+        ast::StringLiteral::Bytes(bs) => match &bs.encoding {
+            ast::StringEncoding::Utf8 => literal,
+            ast::StringEncoding::Binary => format!("{}.b", literal),
+            ast::StringEncoding::UsAscii => {
+                format!("String.new({}, encoding: Encoding::US_ASCII)", literal)
+            }
+            ast::StringEncoding::Other(name) => format!(
+                "String.new({}, encoding: \"{}\")",
+                literal,
+                escape(name, &['"'], true)
+            ),
         },
-        _ => format!("#{{{}}}", Expression(exp).emit()),
+        _ => literal,
+    }
+}
+
+/// Whether the literal is a byte string in other than UTF-8 encoding,
+/// which can't be written without a method call, see `encoded`.
+fn is_encoded(s: &ast::StringLiteral) -> bool {
+    matches!(s, ast::StringLiteral::Bytes(bs) if bs.encoding != ast::StringEncoding::Utf8)
+}
+
+/// Emit symbol literal in its form, e.g. `:sym`, `:"sym"`, `:'sym'` or `%s{sym}`.
+fn symbol_literal(s: &ast::StringLiteral, form: &ast::LiteralForm) -> String {
+    if is_encoded(s) {
+        return format!("{}.to_sym", encoded(s, string_literal(s, form)));
+    }
+    match (form, s) {
+        (ast::LiteralForm::SingleQuoted, ast::StringLiteral::Static(st)) => {
+            format!(":'{}'", escape(st, &['\''], false))
+        }
+        (ast::LiteralForm::Percent(pd), ast::StringLiteral::Static(_)) => {
+            percent_literal("s", s, pd, false)
+        }
+        _ => format!(":{}", SymVal(s).emit()),
+    }
+}
+
+/// Emit percent literal, e.g. `%q{str}`, content is escaped
+/// so it doesn't conflict with the delimiters.
+fn percent_literal(
+    kind: &str,
+    s: &ast::StringLiteral,
+    pd: &ast::PercentDelimiter,
+    interpolate: bool,
+) -> String {
+    format!(
+        "%{}{}{}{}",
+        kind,
+        pd.open,
+        delimited(s, pd.open, interpolate),
+        closing_delimiter(pd.open)
+    )
+}
+
+/// Emit string content between the given opening delimiter and its closing pair.
+fn delimited(s: &ast::StringLiteral, open: char, interpolate: bool) -> String {
+    let delimiters = [open, closing_delimiter(open)];
+    match s {
+        ast::StringLiteral::Static(st) => escape(st, &delimiters, interpolate),
+        ast::StringLiteral::Bytes(bs) => {
+            escape_bytes(bs, |st| escape(st, &delimiters, interpolate))
+        }
+        ast::StringLiteral::WithInterpolation(v) => {
+            interpolation(v, &mut |st| escape(st, &delimiters, interpolate))
+        }
+    }
+}
+
+/// Closing delimiter of a percent literal, bracket delimiters are paired.
+fn closing_delimiter(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c,
+    }
+}
+
+/// Escape backslash and delimiters in static string content,
+/// and sequences which would start an interpolation, e.g. `#{`,
+/// when the literal is interpolated.
+/// Lines after a newline in the content are marked as already placed.
+fn escape(s: &str, delimiters: &[char], interpolate: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let interpolation =
+            interpolate && c == '#' && matches!(chars.peek(), Some('{') | Some('@') | Some('$'));
+        if c == '\\' || delimiters.contains(&c) || interpolation {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        if c == '\n' {
+            escaped.push(PLACED);
+        }
+    }
+    escaped
+}
+
+/// Emit raw bytes, printable characters are escaped by `printable`,
+/// the rest are written as escape sequences, e.g. `\n` or `\xFF`,
+/// so the bytes are kept regardless the output encoding.
+/// Only ASCII characters are printable in other than UTF-8 encoding.
+fn escape_bytes(bs: &ast::ByteString, mut printable: impl FnMut(&str) -> String) -> String {
+    let utf8 = bs.encoding == ast::StringEncoding::Utf8;
+    let mut escaped = String::new();
+    let mut run = String::new();
+    let mut rest = bs.bytes.as_slice();
+    while !rest.is_empty() {
+        let valid = match std::str::from_utf8(rest) {
+            Ok(st) => st.len(),
+            Err(err) => err.valid_up_to(),
+        };
+        let text = std::str::from_utf8(&rest[..valid]).unwrap_or_default();
+        for c in text.chars() {
+            if !c.is_control() && (utf8 || c.is_ascii()) {
+                run.push(c);
+                continue;
+            }
+            escaped.push_str(&printable(&run));
+            run.clear();
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                escaped.push_str(&escape_byte(byte));
+            }
+        }
+        rest = &rest[valid..];
+        if let Some((byte, tail)) = rest.split_first() {
+            escaped.push_str(&printable(&run));
+            run.clear();
+            escaped.push_str(&escape_byte(*byte));
+            rest = tail;
+        }
+    }
+    escaped.push_str(&printable(&run));
+    escaped
+}
+
+fn escape_byte(byte: u8) -> String {
+    match byte {
+        b'\n' => "\\n".to_string(),
+        b'\t' => "\\t".to_string(),
+        b'\r' => "\\r".to_string(),
+        0x07 => "\\a".to_string(),
+        0x08 => "\\b".to_string(),
+        0x0b => "\\v".to_string(),
+        0x0c => "\\f".to_string(),
+        0x1b => "\\e".to_string(),
+        _ => format!("\\x{:02X}", byte),
+    }
+}
+
+fn is_static(s: &ast::StringLiteral) -> bool {
+    matches!(s, ast::StringLiteral::Static(_))
+}
+
+/// Whether string can be written as character literal, e.g. `?a`.
+fn is_character(s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => !c.is_whitespace() && !c.is_control(),
+        _ => false,
     }
 }
 
@@ -69,51 +314,50 @@ impl<'sym> Emitter for SymVal<'sym> {
     fn emit(&self) -> String {
         match self.0 {
             ast::StringLiteral::Static(s) => symbol_quote(s),
-            ast::StringLiteral::WithInterpolation(v) => format!(
-                "\"{}\"",
-                v.iter().fold(String::new(), |buff, exp| {
-                    format!("{}{}", buff, string_interpolate(exp))
-                })
-            ),
+            ast::StringLiteral::Bytes(_) | ast::StringLiteral::WithInterpolation(_) => {
+                format!("\"{}\"", delimited(self.0, '"', true))
+            }
         }
     }
 }
 
 lazy_static! {
-    static ref PROP_SYMBOL: Regex = Regex::new(r"[^0-9a-zA-Z]").unwrap();
+    static ref PROP_SYMBOL: Regex = Regex::new(r"^@@?[_\p{L}][_\p{L}\p{N}]*$").unwrap();
 }
 
+/// Quote symbol unless it can be written as a bare symbol,
+/// e.g. `:foo`, `:valid?`, `:@ivar`, `:$stdout` or `:+`.
 fn symbol_quote(s: &str) -> String {
-    if PROP_SYMBOL.is_match(s) {
-        return format!("{{{}}}", s);
+    let bare = ast::MethodName::parse(s).is_some()
+        || ast::GlobalVariable::parse(s).is_some()
+        || PROP_SYMBOL.is_match(s);
+    if !bare {
+        return format!("\"{}\"", escape(s, &['"'], true));
     }
     s.to_string()
 }
 
+/// Body and terminator of here document are deferred until the end of the line
+/// which opens it, e.g. `foo(<<~EOS, 1)`, they are kept as is since
+/// indentation would change the document or end it too late.
 pub struct HereDoc<'h>(pub(crate) &'h ast::HereDocumentVariants);
 
 impl<'h> Emitter for HereDoc<'h> {
     fn emit(&self) -> String {
-        match self.0 {
-            ast::HereDocumentVariants::Plain(hd) => format!(
-                "<<{}{}{}",
-                hd.enclosure.0,
-                StringVal(&hd.document).emit(),
-                hd.enclosure.0
-            ),
-            ast::HereDocumentVariants::Dash(hd) => format!(
-                "<<-{}\n{}\n{}",
-                hd.enclosure.0,
-                StringVal(&hd.document).emit(),
-                hd.enclosure.0
-            ),
-            ast::HereDocumentVariants::Squiggly(hd) => format!(
-                "<~{}\n{}\n{}",
-                hd.enclosure.0,
+        let (opening, hd) = match self.0 {
+            ast::HereDocumentVariants::Plain(hd) => ("<<", hd),
+            ast::HereDocumentVariants::Dash(hd) => ("<<-", hd),
+            ast::HereDocumentVariants::Squiggly(hd) => ("<<~", hd),
+        };
+        format!(
+            "{}{}",
+            encoded(&hd.document, format!("{}{}", opening, hd.enclosure.0)),
+            deferred(&format!(
+                "{}\n{}\n",
                 StringVal(&hd.document).emit(),
                 hd.enclosure.0
-            ),
-        }
+            ))
+        )
     }
 }
 
@@ -130,12 +374,125 @@ impl<'r> Emitter for RegularExpression<'r> {
                     ast::RegularExpressionFlag::I => "i".to_string(),
                     ast::RegularExpressionFlag::M => "m".to_string(),
                     ast::RegularExpressionFlag::N => "n".to_string(),
+                    ast::RegularExpressionFlag::O => "o".to_string(),
+                    ast::RegularExpressionFlag::S => "s".to_string(),
                     ast::RegularExpressionFlag::U => "u".to_string(),
                     ast::RegularExpressionFlag::X => "x".to_string(),
                 }
             )
         });
-        format!("/{}/{}", StringVal(&self.0.expression).emit(), flags)
+        let open = match &self.0.form {
+            ast::LiteralForm::Percent(pd) if opens_balanced(&self.0.expression, pd.open) => pd.open,
+            _ => regex_delimiter(&self.0.expression),
+        };
+        format!(
+            "{}{}{}{}{}",
+            if open == '/' { "" } else { "%r" },
+            open,
+            regex_body(&self.0.expression, open),
+            closing_delimiter(open),
+            flags
+        )
+    }
+}
+
+/// Use `/` as regular expression delimiter unless its source contains `/`,
+/// in which case pick a `%r` delimiter which doesn't appear in the source.
+fn regex_delimiter(s: &ast::StringLiteral) -> char {
+    let source = regex_static_parts(s);
+    if !source.contains('/') {
+        return '/';
+    }
+    ['{', '(', '[', '<', '|', '!']
+        .iter()
+        .copied()
+        .find(|open| !source.contains(*open) && !source.contains(closing_delimiter(*open)))
+        .unwrap_or('/')
+}
+
+/// Whether every opening bracket delimiter in the source is closed,
+/// unclosed one would leave `%r{` literal unterminated. Unmatched closing
+/// delimiter is escaped by `regex_body`, non bracket delimiter is always fine.
+fn opens_balanced(s: &ast::StringLiteral, open: char) -> bool {
+    let close = closing_delimiter(open);
+    if open == close {
+        return true;
+    }
+    let mut depth = 0;
+    let source = regex_static_parts(s);
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == open {
+            depth += 1;
+        } else if c == close && depth > 0 {
+            depth -= 1;
+        }
+    }
+    depth == 0
+}
+
+fn regex_static_parts(s: &ast::StringLiteral) -> String {
+    match s {
+        ast::StringLiteral::Static(st) => st.clone(),
+        ast::StringLiteral::Bytes(bs) => escape_bytes(bs, |st| st.to_string()),
+        ast::StringLiteral::WithInterpolation(v) => v
+            .iter()
+            .filter_map(|exp| match exp {
+                ast::Expression::Literal(ast::ValueVariants::String(
+                    ast::StringLiteral::Static(st),
+                    _,
+                )) => Some(st.clone()),
+                ast::Expression::Literal(ast::ValueVariants::String(
+                    ast::StringLiteral::Bytes(bs),
+                    _,
+                )) => Some(escape_bytes(bs, |st| st.to_string())),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Emit regular expression source, escaping delimiters which would end the literal.
+/// Source is kept as is otherwise, since its escapes belong to the regular expression.
+/// Balanced bracket delimiters are not escaped, since ruby allows them to nest
+/// and escaping would turn e.g. a `{2}` quantifier into literal braces.
+fn regex_body(s: &ast::StringLiteral, open: char) -> String {
+    let close = closing_delimiter(open);
+    let mut depth = 0;
+    let mut escape_delimiters = |st: &str| {
+        let mut escaped = String::with_capacity(st.len());
+        let mut chars = st.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                escaped.push(c);
+                if let Some(next) = chars.next() {
+                    escaped.push(next);
+                    if next == '\n' {
+                        escaped.push(PLACED);
+                    }
+                }
+                continue;
+            }
+            if open != close && c == open {
+                depth += 1;
+            } else if c == close && depth > 0 {
+                depth -= 1;
+            } else if c == close {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            if c == '\n' {
+                escaped.push(PLACED);
+            }
+        }
+        escaped
+    };
+    match s {
+        ast::StringLiteral::Static(st) => escape_delimiters(st),
+        ast::StringLiteral::Bytes(bs) => escape_bytes(bs, escape_delimiters),
+        ast::StringLiteral::WithInterpolation(v) => interpolation(v, &mut escape_delimiters),
     }
 }
 
@@ -146,29 +503,106 @@ impl<'a> Emitter for ArrayVal<'a> {
         match self.0 {
             ast::ArrayLiteral::Plain(vexp) => format!(
                 "[{}]",
-                vexp.iter()
-                    .map(|exp| format!("{}", Expression(exp).emit()))
-                    .collect::<Vec<String>>()
-                    .join(", ")
+                vexp.iter().map(element).collect::<Vec<String>>().join(", ")
             ),
             ast::ArrayLiteral::Splat(aexp) => array_expression(aexp),
-            ast::ArrayLiteral::WithInterpolation(vaip) => format!(
-                "[{}]",
-                vaip.iter()
-                    .map(|exp| match exp {
-                        ast::ArrayInterpolation::Expression(exp) => Expression(exp).emit(),
-                        ast::ArrayInterpolation::Splat(aexp) => array_expression(aexp),
-                    })
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            ),
+            ast::ArrayLiteral::Words(elts, pd) => {
+                percent_array("w", elts, pd).unwrap_or_else(|| {
+                    format!(
+                        "[{}]",
+                        elts.iter()
+                            .map(|elt| encoded(
+                                elt,
+                                string_literal(elt, &ast::LiteralForm::Default)
+                            ))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+            }
+            ast::ArrayLiteral::Symbols(elts, pd) => {
+                percent_array("i", elts, pd).unwrap_or_else(|| {
+                    format!(
+                        "[{}]",
+                        elts.iter()
+                            .map(|elt| symbol_literal(elt, &ast::LiteralForm::Default))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+            }
+            ast::ArrayLiteral::WithInterpolation(vaip) => {
+                format!("[{}]", array_interpolations(vaip))
+            }
         }
     }
 }
 
 use super::access::Access;
 
-fn array_expression(aexp: &ast::ArrayExpression) -> String {
+/// Emit percent array, e.g. `%w[a b]` or `%I[a#{b} c]`, whitespaces in elements
+/// are escaped. Returns `None` if an element can't be written in this form,
+/// i.e. an empty element, an interpolated element in non-interpolated form,
+/// or a byte string in other than UTF-8 encoding.
+fn percent_array(
+    kind: &str,
+    elts: &[ast::StringLiteral],
+    pd: &ast::PercentDelimiter,
+) -> Option<String> {
+    let delimiters = [pd.open, closing_delimiter(pd.open)];
+    let escape_element = |st: &str| {
+        escape(st, &delimiters, pd.interpolated)
+            .chars()
+            .fold(String::new(), |mut escaped, c| {
+                if c.is_whitespace() {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+                escaped
+            })
+    };
+    let elements = elts
+        .iter()
+        .map(|elt| match elt {
+            ast::StringLiteral::Static(st) if !st.is_empty() => Some(escape_element(st)),
+            // Escape sequences are only allowed in interpolated form.
+            ast::StringLiteral::Bytes(bs)
+                if pd.interpolated && !bs.bytes.is_empty() && !is_encoded(elt) =>
+            {
+                Some(escape_bytes(bs, escape_element))
+            }
+            ast::StringLiteral::WithInterpolation(v) if pd.interpolated && !v.is_empty() => {
+                Some(interpolation(v, &mut |st| escape_element(st)))
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<String>>>()?;
+    Some(format!(
+        "%{}{}{}{}",
+        if pd.interpolated {
+            kind.to_uppercase()
+        } else {
+            kind.to_string()
+        },
+        pd.open,
+        elements.join(" "),
+        closing_delimiter(pd.open)
+    ))
+}
+
+/// Emit comma separated elements which may be splatted,
+/// e.g. elements of array literal, or values of `when` and `return`.
+pub(crate) fn array_interpolations(vaip: &[ast::ArrayInterpolation]) -> String {
+    vaip.iter()
+        .map(|exp| match exp {
+            ast::ArrayInterpolation::Expression(exp) => element(exp),
+            ast::ArrayInterpolation::Splat(aexp) => array_expression(aexp),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub(crate) fn array_expression(aexp: &ast::ArrayExpression) -> String {
     format!(
         "*{}",
         match aexp {
@@ -182,46 +616,54 @@ pub struct HashVal<'h>(pub(crate) &'h ast::HashLiteral);
 
 impl<'h> Emitter for HashVal<'h> {
     fn emit(&self) -> String {
-        format!(
-            "{{\n{}\n}}",
-            match self.0 {
-                ast::HashLiteral::Plain(vh) => vh
-                    .iter()
-                    .map(|elt| hash_element(elt))
-                    .collect::<Vec<String>>()
-                    .join(", "),
-                ast::HashLiteral::Splat(sxp) => hash_expression(sxp),
-                ast::HashLiteral::WithInterpolation(hwp) => hwp
-                    .iter()
-                    .map(|hint| {
-                        match hint {
-                            ast::HashInterpolation::Element(elt) => hash_element(elt),
-                            ast::HashInterpolation::Splat(exp) => hash_expression(exp),
-                        }
-                    })
-                    .collect::<Vec<String>>()
-                    .join(", "),
-            }
-        )
+        let elements = match self.0 {
+            ast::HashLiteral::Plain(vh) => vh.iter().map(hash_element).collect::<Vec<String>>(),
+            ast::HashLiteral::Splat(sxp) => vec![hash_expression(sxp)],
+            ast::HashLiteral::WithInterpolation(hwp) => hwp
+                .iter()
+                .map(|hint| match hint {
+                    ast::HashInterpolation::Element(elt) => hash_element(elt),
+                    ast::HashInterpolation::Splat(exp) => hash_expression(exp),
+                })
+                .collect::<Vec<String>>(),
+        };
+        if elements.is_empty() {
+            return "{}".to_string();
+        }
+        let line = format!("{{ {} }}", elements.join(", "));
+        if !line.contains('\n') && line.len() <= MAX_LINE_WIDTH {
+            return line;
+        }
+        format!("{{\n{}}}", indent(&elements.join(",\n")))
     }
 }
 
-fn hash_element(elt: &ast::HashElement) -> String {
+pub(crate) fn hash_element(elt: &ast::HashElement) -> String {
     match elt {
-        ast::HashElement::Pair(pelt) => format!(
-            "{} => {}",
-            Expression(&pelt.key).emit(),
-            Expression(&pelt.value).emit()
-        ),
-        ast::HashElement::WithLabel(lelt) => format!(
-            "{}: {}",
-            StringVal(&lelt.key).emit(),
-            Expression(&lelt.value).emit()
-        ),
+        ast::HashElement::Pair(pelt) => {
+            format!("{} => {}", element(&pelt.key), element(&pelt.value))
+        }
+        ast::HashElement::WithLabel(lelt) => {
+            format!("{}: {}", label(&lelt.key), element(&lelt.value))
+        }
+        ast::HashElement::Shorthand(v) => format!("{}:", v.0),
+    }
+}
+
+lazy_static! {
+    static ref PROP_LABEL: Regex = Regex::new(r"^[_\p{L}][_\p{L}\p{N}]*[?!]?$").unwrap();
+}
+
+/// Emit label as is when it's a valid bare label, quote it otherwise,
+/// e.g. `"content-type"`.
+fn label(key: &ast::StringLiteral) -> String {
+    match key {
+        ast::StringLiteral::Static(st) if PROP_LABEL.is_match(st) => st.clone(),
+        _ => format!("\"{}\"", delimited(key, '"', true)),
     }
 }
 
-fn hash_expression(exp: &ast::HashExpression) -> String {
+pub(crate) fn hash_expression(exp: &ast::HashExpression) -> String {
     format!(
         "**{}",
         match exp {
@@ -249,3 +691,439 @@ impl<'r> Emitter for RangeVal<'r> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn heredoc(document: &str) -> ast::Expression {
+        ast::Expression::Literal(ast::ValueVariants::HereDocument(
+            ast::HereDocumentVariants::Squiggly(ast::HereDocument {
+                enclosure: ast::Constant("EOS".to_string()),
+                document: ast::StringLiteral::Static(document.to_string()),
+            }),
+        ))
+    }
+
+    #[test]
+    fn heredoc_body_follows_its_line() {
+        let call = call("foo", vec![heredoc("  text"), int(1)]);
+        assert_eq!(emit(&call), "foo(<<~EOS, 1)\n  text\nEOS\n");
+        let strip = send(heredoc("text"), "strip", vec![]);
+        assert_eq!(emit(&strip), "<<~EOS.strip\ntext\nEOS\n");
+    }
+
+    #[test]
+    fn heredoc_bodies_in_order() {
+        let call = call("foo", vec![heredoc("a"), heredoc("b")]);
+        assert_eq!(emit(&call), "foo(<<~EOS, <<~EOS)\na\nEOS\nb\nEOS\n");
+    }
+
+    #[test]
+    fn heredoc_body_is_not_indented() {
+        let def = def(
+            "foo",
+            params(&[]),
+            vec![call("bar", vec![heredoc("text")]), var("x")],
+        );
+        assert_eq!(emit(&def), "def foo\n  bar(<<~EOS)\ntext\nEOS\n  x\nend");
+    }
+
+    fn percent(open: char, interpolated: bool) -> ast::LiteralForm {
+        ast::LiteralForm::Percent(ast::PercentDelimiter::new(open, interpolated).unwrap())
+    }
+
+    fn literal(value: ast::ValueVariants) -> String {
+        emit(&ast::Expression::Literal(value))
+    }
+
+    fn text(value: &str) -> ast::StringLiteral {
+        ast::StringLiteral::Static(value.to_string())
+    }
+
+    fn interpolated(parts: Vec<ast::Expression>) -> ast::StringLiteral {
+        ast::StringLiteral::WithInterpolation(parts)
+    }
+
+    fn string_part(value: &str) -> ast::Expression {
+        ast::Expression::Literal(ast::ValueVariants::String(
+            text(value),
+            ast::LiteralForm::Default,
+        ))
+    }
+
+    #[test]
+    fn percent_strings() {
+        let string = |st, form| literal(ast::ValueVariants::String(text(st), form));
+        assert_eq!(string("a b", percent('{', false)), "%q{a b}");
+        assert_eq!(string("a}b{", percent('{', false)), "%q{a\\}b\\{}");
+        assert_eq!(string("a|b", percent('|', true)), "%Q|a\\|b|");
+        assert_eq!(string("#{x}", percent('(', true)), "%Q(\\#{x})");
+        assert_eq!(string("#{x}", percent('(', false)), "%q(#{x})");
+        let dynamic = interpolated(vec![string_part("a"), var("x")]);
+        assert_eq!(
+            literal(ast::ValueVariants::String(dynamic, percent('(', true))),
+            "%Q(a#{x})"
+        );
+        // Interpolation falls back to the default form.
+        let dynamic = interpolated(vec![string_part("a"), var("x")]);
+        assert_eq!(
+            literal(ast::ValueVariants::String(dynamic, percent('(', false))),
+            "\"a#{x}\""
+        );
+        assert_eq!(
+            literal(ast::ValueVariants::Symbol(text("a b"), percent('[', false))),
+            "%s[a b]"
+        );
+        assert_eq!(
+            literal(ast::ValueVariants::ExecuteString(
+                text("ls"),
+                percent('(', true)
+            )),
+            "%x(ls)"
+        );
+        assert!(ast::PercentDelimiter::new('a', false).is_none());
+        assert!(ast::PercentDelimiter::new(' ', false).is_none());
+    }
+
+    #[test]
+    fn character_literals() {
+        let character = |st| {
+            literal(ast::ValueVariants::String(
+                text(st),
+                ast::LiteralForm::Character,
+            ))
+        };
+        assert_eq!(character("a"), "?a");
+        assert_eq!(character("\\"), "?\\\\");
+        assert_eq!(character("é"), "?é");
+        assert_eq!(character("ab"), "\"ab\"");
+        assert_eq!(character(" "), "\" \"");
+        assert_eq!(character(""), "\"\"");
+    }
+
+    #[test]
+    fn percent_arrays() {
+        let words = |elts: Vec<ast::StringLiteral>, open, interpolated| {
+            let pd = ast::PercentDelimiter::new(open, interpolated).unwrap();
+            literal(ast::ValueVariants::Array(ast::ArrayLiteral::Words(
+                elts, pd,
+            )))
+        };
+        assert_eq!(words(vec![text("a"), text("b")], '[', false), "%w[a b]");
+        assert_eq!(
+            words(vec![text("a b"), text("c]")], '[', false),
+            "%w[a\\ b c\\]]"
+        );
+        let dynamic = interpolated(vec![var("a")]);
+        assert_eq!(words(vec![dynamic, text("b")], '[', true), "%W[#{a} b]");
+        // Not representable in the form, falls back to array literal.
+        let dynamic = interpolated(vec![var("a")]);
+        assert_eq!(
+            words(vec![dynamic, text("b")], '[', false),
+            "[\"#{a}\", \"b\"]"
+        );
+        assert_eq!(
+            words(vec![text(""), text("b")], '(', false),
+            "[\"\", \"b\"]"
+        );
+        let pd = ast::PercentDelimiter::new('[', false).unwrap();
+        let symbols = ast::ArrayLiteral::Symbols(vec![text("x"), text("y")], pd);
+        assert_eq!(literal(ast::ValueVariants::Array(symbols)), "%i[x y]");
+        let pd = ast::PercentDelimiter::new('[', false).unwrap();
+        let symbols = ast::ArrayLiteral::Symbols(vec![text("x"), text("")], pd);
+        assert_eq!(literal(ast::ValueVariants::Array(symbols)), "[:x, :\"\"]");
+    }
+
+    fn regex(
+        source: &str,
+        options: Vec<ast::RegularExpressionFlag>,
+        form: ast::LiteralForm,
+    ) -> String {
+        literal(ast::ValueVariants::RegularExpression(
+            ast::RegularExpression::new(text(source), options, form).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn regex_flags() {
+        let flags = vec![
+            ast::RegularExpressionFlag::I,
+            ast::RegularExpressionFlag::M,
+            ast::RegularExpressionFlag::X,
+            ast::RegularExpressionFlag::O,
+            ast::RegularExpressionFlag::S,
+        ];
+        assert_eq!(regex("a+", flags, ast::LiteralForm::Default), "/a+/imxos");
+        let conflicting = ast::RegularExpression::new(
+            text("a"),
+            vec![ast::RegularExpressionFlag::N, ast::RegularExpressionFlag::U],
+            ast::LiteralForm::Default,
+        );
+        assert!(conflicting.is_none());
+    }
+
+    #[test]
+    fn regex_delimiters() {
+        assert_eq!(regex("a/b", vec![], ast::LiteralForm::Default), "%r{a/b}");
+        assert_eq!(
+            regex("a/{2}", vec![], ast::LiteralForm::Default),
+            "%r(a/{2})"
+        );
+        assert_eq!(
+            regex("/{}()[]<>", vec![], ast::LiteralForm::Default),
+            "%r|/{}()[]<>|"
+        );
+        assert_eq!(
+            regex("/{}()[]<>|!", vec![], ast::LiteralForm::Default),
+            "/\\/{}()[]<>|!/"
+        );
+        assert_eq!(
+            regex("a\\/b", vec![], ast::LiteralForm::Default),
+            "%r{a\\/b}"
+        );
+        // Balanced delimiters are kept, unbalanced ones fall back to the default form.
+        assert_eq!(regex("a{2}", vec![], percent('{', true)), "%r{a{2}}");
+        assert_eq!(regex("a}", vec![], percent('{', true)), "%r{a\\}}");
+        assert_eq!(regex("a{", vec![], percent('{', true)), "/a{/");
+        assert_eq!(regex("a|b", vec![], percent('|', true)), "%r|a\\|b|");
+    }
+
+    fn labeled(key: &str, value: ast::Expression) -> ast::HashElement {
+        ast::HashElement::WithLabel(ast::LabeledElement {
+            key: text(key),
+            value,
+        })
+    }
+
+    fn shorthand(name: &str) -> ast::HashElement {
+        ast::HashElement::Shorthand(ast::Variable(name.to_string()))
+    }
+
+    #[test]
+    fn hash_elements() {
+        let hash = |elts| literal(ast::ValueVariants::Hash(ast::HashLiteral::Plain(elts)));
+        assert_eq!(hash(vec![]), "{}");
+        assert_eq!(hash(vec![shorthand("x"), shorthand("y")]), "{ x:, y: }");
+        assert_eq!(
+            hash(vec![
+                labeled("content-type", string_part("json")),
+                labeled("valid?", int(1)),
+            ]),
+            "{ \"content-type\": \"json\", valid?: 1 }"
+        );
+        let pair = ast::HashElement::Pair(ast::PairElement {
+            key: string_part("k"),
+            value: var("v"),
+        });
+        assert_eq!(hash(vec![pair]), "{ \"k\" => v }");
+        let long = "x".repeat(40);
+        assert_eq!(
+            hash(vec![labeled("a", var(&long)), labeled("b", var(&long))]),
+            format!("{{\n  a: {},\n  b: {}\n}}", long, long)
+        );
+        assert_eq!(
+            shorthand("x").minimum_version(),
+            Some(ast::RubyVersion(3, 1))
+        );
+        assert_eq!(labeled("x", int(1)).minimum_version(), None);
+    }
+
+    #[test]
+    fn keyword_arguments() {
+        let opts = ast::HashExpression::Access(ast::AccessVariants::LocalVariable(ast::Variable(
+            "opts".to_string(),
+        )));
+        let args = ast::SendMethodArgument::new(
+            vec![
+                ast::ArgumentVariants::Expression(var("a")),
+                ast::ArgumentVariants::Keyword(labeled("key", int(1))),
+                ast::ArgumentVariants::Keyword(ast::HashElement::Pair(ast::PairElement {
+                    key: string_part("k"),
+                    value: var("v"),
+                })),
+                ast::ArgumentVariants::Keyword(shorthand("x")),
+                ast::ArgumentVariants::KeywordSplat(opts),
+            ],
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(args.minimum_version(), Some(ast::RubyVersion(3, 1)));
+        let keywords =
+            ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method("foo", args)));
+        assert_eq!(emit(&keywords), "foo(a, key: 1, \"k\" => v, x:, **opts)");
+        let hash =
+            ast::Expression::Literal(ast::ValueVariants::Hash(ast::HashLiteral::Plain(vec![
+                labeled("a", int(1)),
+            ])));
+        assert_eq!(emit(&call("foo", vec![hash])), "foo({ a: 1 })");
+        let positional = ast::SendMethodArgument::new(
+            vec![
+                ast::ArgumentVariants::Keyword(shorthand("x")),
+                ast::ArgumentVariants::Expression(var("a")),
+            ],
+            None,
+            None,
+        );
+        assert!(positional.is_none());
+    }
+
+    fn bytes(source: &str, encoding: ast::StringEncoding) -> ast::StringLiteral {
+        ast::StringLiteral::Bytes(ast::ByteString::unescape(source, encoding).unwrap())
+    }
+
+    /// Emit byte string and check its content decodes to the same bytes.
+    fn round_trip(source: &str) -> String {
+        let decoded = ast::ByteString::unescape(source, ast::StringEncoding::Utf8).unwrap();
+        let emitted = literal(ast::ValueVariants::String(
+            bytes(source, ast::StringEncoding::Utf8),
+            ast::LiteralForm::Default,
+        ));
+        let content = &emitted[1..emitted.len() - 1];
+        let again = ast::ByteString::unescape(content, ast::StringEncoding::Utf8).unwrap();
+        assert_eq!(again.bytes, decoded.bytes);
+        emitted
+    }
+
+    #[test]
+    fn byte_string_round_trip() {
+        assert_eq!(round_trip("\\M-\\C-x"), "\"\\x98\"");
+        assert_eq!(round_trip("\\C-?\\cA"), "\"\\x7F\\x01\"");
+        assert_eq!(round_trip("\\u{e9 1F600}\\u0041"), "\"é😀A\"");
+        assert_eq!(round_trip("\\101\\0\\7"), "\"A\\x00\\a\"");
+        assert_eq!(round_trip("\\x7\\xFFz"), "\"\\a\\xFFz\"");
+        // Unknown escape is the character itself.
+        assert_eq!(round_trip("\\q\\\\\\\""), "\"q\\\\\\\"\"");
+        assert!(ast::ByteString::unescape("\\x", ast::StringEncoding::Utf8).is_none());
+        // `\u` escape makes the literal UTF-8.
+        let unicode = ast::ByteString::unescape("\\u00e9", ast::StringEncoding::Binary).unwrap();
+        assert_eq!(unicode.encoding, ast::StringEncoding::Utf8);
+    }
+
+    #[test]
+    fn byte_string_encoding() {
+        let string = |encoding| {
+            literal(ast::ValueVariants::String(
+                bytes("\\xFFa", encoding),
+                ast::LiteralForm::Default,
+            ))
+        };
+        assert_eq!(string(ast::StringEncoding::Binary), "\"\\xFFa\".b");
+        assert_eq!(
+            string(ast::StringEncoding::UsAscii),
+            "String.new(\"\\xFFa\", encoding: Encoding::US_ASCII)"
+        );
+        assert_eq!(
+            string(ast::StringEncoding::Other("Shift_JIS".to_string())),
+            "String.new(\"\\xFFa\", encoding: \"Shift_JIS\")"
+        );
+        let symbol = ast::ValueVariants::Symbol(
+            bytes("\\xFF", ast::StringEncoding::Binary),
+            ast::LiteralForm::Default,
+        );
+        assert_eq!(literal(symbol), "\"\\xFF\".b.to_sym");
+    }
+
+    #[test]
+    fn percent_array_keeps_encoding() {
+        let pd = || ast::PercentDelimiter::new('[', true).unwrap();
+        let binary = || bytes("\\xFF", ast::StringEncoding::Binary);
+        let words = ast::ArrayLiteral::Words(vec![binary(), text("a")], pd());
+        assert_eq!(
+            literal(ast::ValueVariants::Array(words)),
+            "[\"\\xFF\".b, \"a\"]"
+        );
+        let symbols = ast::ArrayLiteral::Symbols(vec![binary(), text("a")], pd());
+        assert_eq!(
+            literal(ast::ValueVariants::Array(symbols)),
+            "[\"\\xFF\".b.to_sym, :a]"
+        );
+        let utf8 = ast::ArrayLiteral::Words(vec![bytes("\\x00", ast::StringEncoding::Utf8)], pd());
+        assert_eq!(literal(ast::ValueVariants::Array(utf8)), "%W[\\x00]");
+    }
+
+    fn ivar(name: &str) -> ast::Expression {
+        ast::Expression::Access(ast::AccessVariants::InstanceVariable(ast::Variable(
+            name.to_string(),
+        )))
+    }
+
+    fn string(parts: Vec<ast::Expression>) -> String {
+        literal(ast::ValueVariants::String(
+            interpolated(parts),
+            ast::LiteralForm::Default,
+        ))
+    }
+
+    #[test]
+    fn interpolated_variables() {
+        assert_eq!(string(vec![string_part("a "), ivar("b")]), "\"a #@b\"");
+        let gvar = ast::Expression::Access(ast::AccessVariants::GlobalVariable(
+            ast::GlobalVariable::parse("$stdout").unwrap(),
+        ));
+        assert_eq!(string(vec![gvar, string_part("!")]), "\"#$stdout!\"");
+        // Shorthand would take the following text as a part of the name.
+        assert_eq!(string(vec![ivar("b"), string_part("c")]), "\"#{@b}c\"");
+        assert_eq!(string(vec![ivar("b"), string_part("é")]), "\"#{@b}é\"");
+        assert_eq!(string(vec![ivar("b"), var("x")]), "\"#@b#{x}\"");
+        // Local variable has no shorthand.
+        assert_eq!(string(vec![var("a")]), "\"#{a}\"");
+    }
+
+    #[test]
+    fn interpolated_statements() {
+        let nested = ast::Expression::Literal(ast::ValueVariants::String(
+            interpolated(vec![string_part("\""), var("b")]),
+            ast::LiteralForm::Default,
+        ));
+        assert_eq!(string(vec![string_part("a"), nested]), "\"a\\\"#{b}\"");
+        let quoted = ast::Expression::Literal(ast::ValueVariants::String(
+            text("\""),
+            ast::LiteralForm::SingleQuoted,
+        ));
+        let quoted = ast::Expression::Operation(ast::OperationVariants::Paren(body(vec![send(
+            quoted,
+            "upcase",
+            vec![],
+        )])));
+        assert_eq!(string(vec![quoted]), "\"#{'\"'.upcase}\"");
+        let statements = ast::Expression::Operation(ast::OperationVariants::Paren(body(vec![
+            assign("a", int(1)),
+            if_(var("b"), vec![var("a")], ast::ModifierForm::Modifier),
+        ])));
+        assert_eq!(string(vec![statements]), "\"#{a = 1; a if b}\"");
+        let modifier = if_(var("b"), vec![var("a")], ast::ModifierForm::Auto);
+        assert_eq!(string(vec![modifier]), "\"#{a if b}\"");
+    }
+
+    #[test]
+    fn interpolated_literals() {
+        let parts = || interpolated(vec![string_part("a "), var("b")]);
+        assert_eq!(
+            literal(ast::ValueVariants::Symbol(
+                parts(),
+                ast::LiteralForm::Default
+            )),
+            ":\"a #{b}\""
+        );
+        assert_eq!(
+            literal(ast::ValueVariants::ExecuteString(
+                parts(),
+                ast::LiteralForm::Default
+            )),
+            "`a #{b}`"
+        );
+        assert_eq!(
+            literal(ast::ValueVariants::RegularExpression(
+                ast::RegularExpression::new(parts(), vec![], ast::LiteralForm::Default).unwrap(),
+            )),
+            "/a #{b}/"
+        );
+        assert_eq!(
+            literal(ast::ValueVariants::String(parts(), percent('(', true))),
+            "%Q(a #{b})"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::access::Access;
+use super::assignment::multiple_left_hand_side_element;
+use super::literals::ArrayVal;
+use super::{block_form, body, loop_head, Emitter};
+use crate::ast;
+
+pub struct Loop<'l>(pub(crate) &'l ast::LoopVariants);
+
+impl<'l> Emitter for Loop<'l> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::LoopVariants::PreCondition(lcv) => {
+                let (keyword, ls) = loop_condition(lcv);
+                block_form(keyword, &ls.condition, &ls.body.0)
+            }
+            ast::LoopVariants::PostCondition(lcv) => {
+                let (keyword, ls) = loop_condition(lcv);
+                format!(
+                    "begin\n{}end {} {}",
+                    body(&ls.body.0),
+                    keyword,
+                    super::expression::Expression(&ls.condition).emit()
+                )
+            }
+            ast::LoopVariants::ForIn(fl) => format!(
+                "for {} in {}\n{}end",
+                multiple_left_hand_side_element(&fl.assignee),
+                loop_head(
+                    match &fl.iterator {
+                        ast::ArrayExpression::Access(acc) => Access(acc).emit(),
+                        ast::ArrayExpression::Literal(al) => ArrayVal(al).emit(),
+                    },
+                    &fl.iterator.children()
+                ),
+                body(&fl.body.0)
+            ),
+        }
+    }
+}
+
+/// Emit `while` or `until` loop in modifier form, see `super::modifier`.
+pub(crate) fn modifier(l: &ast::LoopVariants) -> Option<String> {
+    match l {
+        ast::LoopVariants::PreCondition(lcv) => {
+            let (keyword, ls) = loop_condition(lcv);
+            match ls.body.0.as_slice() {
+                // `begin ... end while cond` is a post-condition loop,
+                // keep the block form so the semantic doesn't change.
+                [stmt] if emits_begin(stmt) => None,
+                _ => super::modifier(keyword, &ls.condition, &ls.body.0, &ls.form),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn loop_condition(lcv: &ast::LoopConditionVariants) -> (&'static str, &ast::LoopStruct) {
+    match lcv {
+        ast::LoopConditionVariants::While(ls) => ("while", ls),
+        ast::LoopConditionVariants::Until(ls) => ("until", ls),
+    }
+}
+
+/// Whether the statement is emitted as `begin ... end`,
+/// implicit exception handling is also emitted so in a loop body.
+fn emits_begin(stmt: &ast::Expression) -> bool {
+    match stmt {
+        ast::Expression::Commented(_, exp) => emits_begin(exp),
+        ast::Expression::Verbatim(v) => emits_begin(&v.expression),
+        ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Begin(_))
+        | ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Implicit(_)) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn while_(
+        condition: ast::Expression,
+        expressions: Vec<ast::Expression>,
+        form: ast::ModifierForm,
+    ) -> ast::LoopConditionVariants {
+        ast::LoopConditionVariants::While(ast::LoopStruct {
+            condition: Box::new(condition),
+            body: body(expressions),
+            form,
+        })
+    }
+
+    fn less(left: ast::Expression, right: ast::Expression) -> ast::Expression {
+        send(left, "<", vec![right])
+    }
+
+    fn increment() -> ast::Expression {
+        ast::Expression::Assignment(ast::AssignmentVariants::BinaryOperator(
+            ast::BinaryOperator::Add,
+            ast::AccessVariants::LocalVariable(ast::Variable("x".to_string())),
+            Box::new(int(1)),
+        ))
+    }
+
+    #[test]
+    fn modifier_loops() {
+        let auto = while_(
+            less(var("x"), int(10)),
+            vec![increment()],
+            ast::ModifierForm::Auto,
+        );
+        assert_eq!(
+            emit(&ast::Expression::Loop(ast::LoopVariants::PreCondition(
+                auto
+            ))),
+            "x += 1 while x < 10"
+        );
+        let block = while_(
+            less(var("x"), int(10)),
+            vec![increment()],
+            ast::ModifierForm::Block,
+        );
+        assert_eq!(
+            emit(&ast::Expression::Loop(ast::LoopVariants::PreCondition(
+                block
+            ))),
+            "while x < 10\n  x += 1\nend"
+        );
+    }
+
+    #[test]
+    fn post_condition_loop() {
+        let post = while_(
+            less(var("x"), int(10)),
+            vec![increment()],
+            ast::ModifierForm::Auto,
+        );
+        assert_eq!(
+            emit(&ast::Expression::Loop(ast::LoopVariants::PostCondition(
+                post
+            ))),
+            "begin\n  x += 1\nend while x < 10"
+        );
+        // `begin ... end` body of a pre-condition loop keeps the block form.
+        let begin = ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Begin(
+            ast::ExceptionHandling::new(body(vec![var("a")]), vec![], None, Some(body(vec![])))
+                .unwrap(),
+        ));
+        let pre = while_(var("c"), vec![begin], ast::ModifierForm::Modifier);
+        assert_eq!(
+            emit(&ast::Expression::Loop(ast::LoopVariants::PreCondition(pre))),
+            "while c\n  begin\n    a\n  ensure\n  end\nend"
+        );
+    }
+
+    #[test]
+    fn condition_assigning_local_keeps_block_form() {
+        let line = while_(
+            assign("line", call("gets", vec![])),
+            vec![call("puts", vec![var("line")])],
+            ast::ModifierForm::Auto,
+        );
+        assert_eq!(
+            emit(&ast::Expression::Loop(ast::LoopVariants::PreCondition(
+                line
+            ))),
+            "while line = gets\n  puts(line)\nend"
+        );
+    }
+}
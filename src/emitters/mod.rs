@@ -1,7 +1,266 @@
 pub mod access;
+pub mod arguments;
+pub mod assignment;
+pub mod blocks;
+pub mod branching;
+pub mod case_matching;
+pub mod comments;
+pub mod control_flow;
+pub mod definitions;
+pub mod exception_handling;
 pub mod expression;
 pub mod literals;
+pub mod loops;
+pub mod operation;
+pub mod root;
+pub mod send_method;
+#[cfg(test)]
+pub(crate) mod testing;
+
+use crate::ast;
 
 pub trait Emitter {
     fn emit(&self) -> String;
 }
+
+/// Emit expressions as the body of a block-like construct,
+/// one expression per line, indented by two spaces.
+pub(crate) fn body(expressions: &[ast::Expression]) -> String {
+    expressions
+        .iter()
+        .map(|exp| indent(&statement(exp)))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+/// Emit body of `def`, `class`, `module` or `do ... end` block, where implicit
+/// exception handling puts its rescue, else and ensure clauses at the same
+/// level as the enclosing keyword. Anywhere else it's emitted as `begin ... end`.
+pub(crate) fn definition_body(expressions: &[ast::Expression]) -> String {
+    if let [ast::Expression::ExceptionHandling(ast::ExceptionHandlingVariants::Implicit(eh))] =
+        expressions
+    {
+        return exception_handling::clauses(eh);
+    }
+    body(expressions)
+}
+
+/// Emit expression as a statement, along with its comments if any,
+/// or its original source if it's kept verbatim.
+pub(crate) fn statement(exp: &ast::Expression) -> String {
+    match exp {
+        ast::Expression::Commented(comments, exp) => comments::commented(comments, exp),
+        // Original lines already have their indentation.
+        ast::Expression::Verbatim(v) => match &v.text {
+            Some(text) => placed(&format!("{}{}", v.leading, text)),
+            None => format!("{}{}", placed(&v.leading), statement(&v.expression)),
+        },
+        _ => bare_statement(exp),
+    }
+}
+
+/// Emit expression as a statement without its comments. Conditionals and loops
+/// take their modifier form only as statements or in parentheses, since
+/// `x = 1 if c` assigns nothing unless `c`, while `x = (1 if c)` assigns `nil`.
+pub(crate) fn bare_statement(exp: &ast::Expression) -> String {
+    modifier_form(exp).unwrap_or_else(|| expression::Expression(exp).emit())
+}
+
+/// Emit conditional or loop in its modifier form, e.g. `stmt if cond`,
+/// returns `None` if it's kept in block form, see `modifier`.
+pub(crate) fn modifier_form(exp: &ast::Expression) -> Option<String> {
+    match exp {
+        ast::Expression::Branching(b) => branching::modifier(b),
+        ast::Expression::Loop(l) => loops::modifier(l),
+        _ => None,
+    }
+}
+
+/// Indent each non-empty line by two spaces, every line ends with newline.
+/// Lines which are already placed are kept as is, see `PLACED`.
+pub(crate) fn indent(code: &str) -> String {
+    code.split_inclusive('\n')
+        .map(|line| {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            if line.is_empty() || line.starts_with(PLACED) {
+                format!("{}\n", line)
+            } else {
+                format!("  {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// Mark at the beginning of a line which is already at its final column,
+/// e.g. a continuation line of a multi-line string or a here document body,
+/// where indentation would change the string. Marks are removed by `unmark`
+/// once the whole source is emitted.
+pub(crate) const PLACED: char = '\u{0}';
+
+/// Mark every line of `code` as already placed.
+pub(crate) fn placed(code: &str) -> String {
+    code.split_inclusive('\n')
+        .map(|line| format!("{}{}", PLACED, line))
+        .collect()
+}
+
+/// Marks around code deferred until the end of its line, e.g. the body of
+/// a here document, which starts on the line after `foo(<<~EOS, 1)`.
+/// Newlines of deferred code are replaced so it stays on its opening line
+/// through indentation, marks are resolved by `unmark`.
+const DEFERRED_BEGIN: char = '\u{1}';
+const DEFERRED_END: char = '\u{2}';
+const DEFERRED_NEWLINE: char = '\u{3}';
+
+/// Defer lines of `code` until the end of the line it's emitted on.
+pub(crate) fn deferred(code: &str) -> String {
+    format!(
+        "{}{}{}",
+        DEFERRED_BEGIN,
+        code.replace('\n', &DEFERRED_NEWLINE.to_string()),
+        DEFERRED_END
+    )
+}
+
+/// Remove marks of already placed lines, and move deferred code
+/// after the end of its line.
+pub(crate) fn unmark(code: &str) -> String {
+    code.split_inclusive('\n')
+        .map(|line| match undefer(line) {
+            Some(lines) => unmark(&lines),
+            None => line.strip_prefix(PLACED).unwrap_or(line).to_string(),
+        })
+        .collect()
+}
+
+/// Split line into itself followed by its deferred code, in order of appearance,
+/// code deferred within deferred code is kept for the next pass.
+/// Returns `None` if nothing is deferred.
+fn undefer(line: &str) -> Option<String> {
+    if !line.contains(DEFERRED_BEGIN) {
+        return None;
+    }
+    let (mut text, mut deferred) = (String::new(), String::new());
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            DEFERRED_BEGIN => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            DEFERRED_END => {
+                depth -= 1;
+                if depth == 0 {
+                    continue;
+                }
+            }
+            DEFERRED_NEWLINE if depth == 1 => {
+                deferred.push('\n');
+                continue;
+            }
+            _ => (),
+        }
+        if depth == 0 {
+            text.push(c);
+        } else {
+            deferred.push(c);
+        }
+    }
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Some(text + &deferred)
+}
+
+/// Preferred maximum line width, used to decide whether
+/// a construct can be emitted in its single line form.
+pub(crate) const MAX_LINE_WIDTH: usize = 80;
+
+/// Emit body as a single line if it only has one statement
+/// which fits in one line and has no comment.
+pub(crate) fn single_line(expressions: &[ast::Expression]) -> Option<String> {
+    match expressions {
+        [ast::Expression::Commented(..)] => None,
+        [ast::Expression::Verbatim(v)] if !v.leading.is_empty() => None,
+        [exp] => {
+            let line = bare_statement(exp);
+            if line.contains('\n') {
+                return None;
+            }
+            Some(line)
+        }
+        _ => None,
+    }
+}
+
+/// Emit statements with conditional or loop keyword in modifier form,
+/// e.g. `stmt if cond`, returns `None` if its `ModifierForm` keeps the block form.
+pub(crate) fn modifier(
+    keyword: &str,
+    condition: &ast::Expression,
+    stmts: &[ast::Expression],
+    form: &ast::ModifierForm,
+) -> Option<String> {
+    let head = expression::Expression(condition).emit();
+    match (form, stmts) {
+        (ast::ModifierForm::Modifier, [stmt]) => with_modifier(stmt, keyword, &head),
+        (ast::ModifierForm::Auto, _) => {
+            // The statement is parsed before its condition, so a local variable
+            // first assigned in the condition would be taken as a method call in it,
+            // e.g. `puts line while line = gets`.
+            if condition.any(&|exp| matches!(exp, ast::Expression::Assignment(_))) {
+                return None;
+            }
+            let line = format!("{} {} {}", single_line(stmts)?, keyword, head);
+            if head.contains('\n') || line.len() > MAX_LINE_WIDTH {
+                return None;
+            }
+            Some(line)
+        }
+        _ => None,
+    }
+}
+
+/// Emit conditional or loop in block form, e.g. `if cond ... end`.
+pub(crate) fn block_form(
+    keyword: &str,
+    condition: &ast::Expression,
+    stmts: &[ast::Expression],
+) -> String {
+    let head = expression::Expression(condition).emit();
+    let head = match keyword {
+        "while" | "until" => loop_head(head, &[condition]),
+        _ => head,
+    };
+    format!("{} {}\n{}end", keyword, head, body(stmts))
+}
+
+/// Emit statement in modifier form along with its comments,
+/// returns `None` for verbatim statement since its original source is kept as is.
+fn with_modifier(stmt: &ast::Expression, keyword: &str, condition: &str) -> Option<String> {
+    match stmt {
+        ast::Expression::Commented(cmts, exp) => Some(comments::around(
+            cmts,
+            &with_modifier(exp, keyword, condition)?,
+        )),
+        ast::Expression::Verbatim(_) => None,
+        _ => Some(format!(
+            "{} {} {}",
+            bare_statement(stmt),
+            keyword,
+            condition
+        )),
+    }
+}
+
+/// Wrap the condition of `while`, `until` or the iterator of `for` in parentheses
+/// when `nested`, expressions of the condition or iterator, have a `do ... end` block,
+/// otherwise `do` would be taken as the loop's.
+pub(crate) fn loop_head(head: String, nested: &[&ast::Expression]) -> String {
+    if nested.iter().any(|exp| exp.any(&blocks::has_do_block)) {
+        return format!("({})", head);
+    }
+    head
+}
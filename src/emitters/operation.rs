@@ -0,0 +1,153 @@
+use super::expression::primary;
+use super::literals::RegularExpression;
+use super::{body, single_line, Emitter};
+use crate::ast;
+
+pub struct Operation<'o>(pub(crate) &'o ast::OperationVariants);
+
+impl<'o> Emitter for Operation<'o> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::OperationVariants::Paren(stmts) => match single_line(&stmts.0) {
+                Some(line) => format!("({})", line),
+                None => format!("(\n{})", body(&stmts.0)),
+            },
+            ast::OperationVariants::BinaryExpression(beo) => format!(
+                "{} {} {}",
+                primary(&beo.lefthand),
+                beo.operator.spelling(),
+                primary(&beo.righthand)
+            ),
+            ast::OperationVariants::Not(exp) => format!("not {}", primary(exp)),
+        }
+    }
+}
+
+pub struct LogicalOperation<'l>(pub(crate) &'l ast::LogicalOperationVariants);
+
+impl<'l> Emitter for LogicalOperation<'l> {
+    fn emit(&self) -> String {
+        let (left, operator, right) = match self.0 {
+            ast::LogicalOperationVariants::Equal(left, right) => (left, "==", right),
+            ast::LogicalOperationVariants::And(left, right)
+            | ast::LogicalOperationVariants::DoubleAmpersands(left, right) => {
+                (left, ast::LogicalOperator::And.spelling(), right)
+            }
+            ast::LogicalOperationVariants::LowerPrecedenceAnd(left, right) => (left, "and", right),
+            ast::LogicalOperationVariants::Or(left, right)
+            | ast::LogicalOperationVariants::DoublePipes(left, right) => {
+                (left, ast::LogicalOperator::Or.spelling(), right)
+            }
+            ast::LogicalOperationVariants::LowerPrecedenceOr(left, right) => (left, "or", right),
+            ast::LogicalOperationVariants::Not(exp) => return format!("!{}", primary(exp)),
+            ast::LogicalOperationVariants::Match(rem) => {
+                return format!(
+                    "{} =~ {}",
+                    RegularExpression(&rem.regex).emit(),
+                    primary(&rem.expression)
+                )
+            }
+        };
+        format!("{} {} {}", primary(left), operator, primary(right))
+    }
+}
+
+/// Emit flip-flop as the condition of `if`, the only place it's meaningful,
+/// e.g. `if (line =~ /begin/)..(line =~ /end/)`.
+pub struct FlipFlop<'f>(pub(crate) &'f ast::FlipFlopVariants);
+
+impl<'f> Emitter for FlipFlop<'f> {
+    fn emit(&self) -> String {
+        let (ff, range) = match self.0 {
+            ast::FlipFlopVariants::Inclusive(ff) => (ff, ".."),
+            ast::FlipFlopVariants::Exclusive(ff) => (ff, "..."),
+        };
+        format!(
+            "if {}{}{}\n{}end",
+            primary(&ff.flip),
+            range,
+            primary(&ff.flop),
+            body(&ff.body.0)
+        )
+    }
+}
+
+/// Emit `BEGIN { ... }` or `END { ... }` block.
+pub(crate) fn special_block(keyword: &str, stmts: &ast::Body) -> String {
+    format!("{} {{\n{}}}", keyword, body(&stmts.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use crate::ast;
+
+    fn logical(
+        variant: fn(Box<ast::Expression>, Box<ast::Expression>) -> ast::LogicalOperationVariants,
+    ) -> String {
+        emit(&ast::Expression::LogicalOperation(variant(
+            Box::new(var("a")),
+            Box::new(var("b")),
+        )))
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert_eq!(logical(ast::LogicalOperationVariants::Equal), "a == b");
+        assert_eq!(logical(ast::LogicalOperationVariants::And), "a && b");
+        assert_eq!(logical(ast::LogicalOperationVariants::Or), "a || b");
+        assert_eq!(
+            logical(ast::LogicalOperationVariants::DoubleAmpersands),
+            "a && b"
+        );
+        assert_eq!(
+            logical(ast::LogicalOperationVariants::DoublePipes),
+            "a || b"
+        );
+        assert_eq!(
+            logical(ast::LogicalOperationVariants::LowerPrecedenceAnd),
+            "a and b"
+        );
+        assert_eq!(
+            logical(ast::LogicalOperationVariants::LowerPrecedenceOr),
+            "a or b"
+        );
+    }
+
+    #[test]
+    fn bitwise_operators_are_binary() {
+        let and = binary(var("a"), ast::BinaryOperator::And, var("b"));
+        let or = binary(var("a"), ast::BinaryOperator::Or, var("b"));
+        assert_eq!(emit(&and), "a & b");
+        assert_eq!(emit(&or), "a | b");
+    }
+
+    #[test]
+    fn nested_operands_are_parenthesized() {
+        let and = ast::Expression::LogicalOperation(ast::LogicalOperationVariants::And(
+            Box::new(var("a")),
+            Box::new(ast::Expression::LogicalOperation(
+                ast::LogicalOperationVariants::Or(Box::new(var("b")), Box::new(var("c"))),
+            )),
+        ));
+        assert_eq!(emit(&and), "a && (b || c)");
+        let not = ast::Expression::LogicalOperation(ast::LogicalOperationVariants::Not(Box::new(
+            binary(var("a"), ast::BinaryOperator::Add, int(1)),
+        )));
+        assert_eq!(emit(&not), "!(a + 1)");
+        let not = ast::Expression::Operation(ast::OperationVariants::Not(Box::new(var("a"))));
+        assert_eq!(emit(&not), "not a");
+    }
+
+    #[test]
+    fn paren() {
+        let single =
+            ast::Expression::Operation(ast::OperationVariants::Paren(body(vec![var("a")])));
+        assert_eq!(emit(&single), "(a)");
+        let multiple = ast::Expression::Operation(ast::OperationVariants::Paren(body(vec![
+            var("a"),
+            var("b"),
+        ])));
+        assert_eq!(emit(&multiple), "(\n  a\n  b\n)");
+    }
+}
@@ -0,0 +1,239 @@
+use super::comments::{comment, magic_comment, magic_comment_order};
+use super::{statement, unmark, Emitter};
+use crate::ast;
+
+/// Emit the whole source, shebang and magic comments come first in canonical order,
+/// followed by an empty line, then expressions and the data section.
+/// Original source is printed as is if the tree is unchanged.
+pub struct Root<'r>(pub(crate) &'r ast::Root);
+
+impl<'r> Emitter for Root<'r> {
+    fn emit(&self) -> String {
+        let root = self.0;
+        if let Some(source) = &root.source {
+            return source.clone();
+        }
+        let mut code = root
+            .shebang
+            .as_ref()
+            .map_or(String::new(), |shebang| format!("#!{}\n", shebang));
+        let mut magic_comments = root.magic_comments.iter().collect::<Vec<_>>();
+        magic_comments.sort_by_key(|mc| magic_comment_order(mc));
+        for mc in &magic_comments {
+            code.push_str(&format!("{}\n", magic_comment(mc)));
+        }
+        // Verbatim statement keeps its original leading lines.
+        if !magic_comments.is_empty()
+            && !root.expressions.is_empty()
+            && !matches!(root.expressions[0], ast::Expression::Verbatim(_))
+        {
+            code.push('\n');
+        }
+        for exp in &root.expressions {
+            code.push_str(&format!("{}\n", statement(exp)));
+        }
+        for c in &root.comments {
+            code.push_str(&format!("{}\n", comment(c)));
+        }
+        if let Some(data) = &root.data {
+            code.push_str(&format!("__END__\n{}", data));
+        }
+        unmark(&code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use super::*;
+
+    #[test]
+    fn magic_comments_in_canonical_order() {
+        let mut root = ast::Root::new(Vec::new());
+        root.set_magic_comment(ast::MagicComment::Typed(ast::TypedSigil::Strict));
+        root.set_magic_comment(ast::MagicComment::FrozenStringLiteral(true));
+        root.set_magic_comment(ast::MagicComment::Encoding("utf-8".to_string()));
+        root.set_shebang(Some("/usr/bin/env ruby".to_string()));
+        assert_eq!(
+            Root(&root).emit(),
+            "#!/usr/bin/env ruby\n\
+             # encoding: utf-8\n\
+             # frozen_string_literal: true\n\
+             # typed: strict\n"
+        );
+    }
+
+    #[test]
+    fn data_section_after_end() {
+        let mut root = ast::Root::new(vec![ast::Expression::Redo]);
+        root.set_data(Some("raw\n".to_string()));
+        assert_eq!(Root(&root).emit(), "redo\n__END__\nraw\n");
+    }
+
+    fn class(name: &str, expressions: Vec<ast::Expression>) -> ast::Expression {
+        ast::Expression::ClassDefinition(ast::ClassDefinitionVariants::Class(
+            ast::ClassDefinition {
+                name: ast::ConstantVariants::Unscoped(ast::Constant(name.to_string())),
+                parent: None,
+                body: body(expressions),
+            },
+        ))
+    }
+
+    fn line(text: &str) -> ast::CommentVariants {
+        ast::CommentVariants::Line(text.to_string())
+    }
+
+    fn lines(range: std::ops::RangeInclusive<usize>) -> ast::StatementLines {
+        ast::StatementLines::new(range, Vec::new())
+    }
+
+    #[test]
+    fn comments_attach_to_innermost_statement() {
+        let greet = def("greet", params(&[]), vec![ast::Expression::Redo]);
+        let class = class("Bar", vec![greet]);
+        let module = ast::Expression::ModuleDefinition(ast::ModuleDefinition {
+            name: ast::ConstantVariants::Unscoped(ast::Constant("Foo".to_string())),
+            body: ast::Body(vec![class]),
+        });
+        let mut root = ast::Root::new(vec![module]);
+        root.attach_comments(
+            vec![
+                (2, line(" :nodoc:")),
+                (3, line(" Greets.")),
+                (4, line(" @return [nil]")),
+                (6, line(" again")),
+                (8, line(" before end")),
+                (11, line(" end of file")),
+            ],
+            &[ast::StatementLines::new(
+                1..=10,
+                vec![ast::StatementLines::new(
+                    2..=9,
+                    vec![ast::StatementLines::new(5..=7, vec![lines(6..=6)])],
+                )],
+            )],
+        );
+        assert_eq!(
+            Root(&root).emit(),
+            "module Foo\n  \
+             # :nodoc:\n  \
+             # before end\n  \
+             class Bar\n    \
+             # Greets.\n    \
+             # @return [nil]\n    \
+             def greet\n      \
+             redo # again\n    \
+             end\n  \
+             end\n\
+             end\n\
+             # end of file\n"
+        );
+    }
+
+    #[test]
+    fn comments_between_top_level_statements() {
+        let mut root = ast::Root::new(vec![ast::Expression::Redo, ast::Expression::Retry]);
+        root.attach_comments(
+            vec![(1, line(" first")), (2, line(" doc")), (4, line(" last"))],
+            &[lines(1..=1), lines(3..=3)],
+        );
+        assert_eq!(Root(&root).emit(), "redo # first\n# doc\nretry\n# last\n");
+    }
+
+    const SOURCE: &str = "class Foo\n  \
+                          def a\n    \
+                          1 +   2\n  \
+                          end\n\n  \
+                          # doc\n  \
+                          def b\n    \
+                          x\n  \
+                          end\n\
+                          end\n";
+
+    /// Tree of `SOURCE` where every statement keeps its original lines.
+    fn verbatim_root() -> ast::Root {
+        let sum = binary(int(1), ast::BinaryOperator::Add, int(2));
+        let a = def(
+            "a",
+            params(&[]),
+            vec![sum.verbatim(String::new(), "    1 +   2".to_string())],
+        )
+        .verbatim(String::new(), "  def a\n    1 +   2\n  end".to_string());
+        let b = def(
+            "b",
+            params(&[]),
+            vec![var("x").verbatim(String::new(), "    x".to_string())],
+        )
+        .verbatim(
+            "\n  # doc\n".to_string(),
+            "  def b\n    x\n  end".to_string(),
+        );
+        let foo = class("Foo", vec![a, b]).verbatim(String::new(), SOURCE.trim_end().to_string());
+        let mut root = ast::Root::new(vec![foo]);
+        root.set_source(SOURCE.to_string());
+        root
+    }
+
+    #[test]
+    fn unchanged_tree_prints_source() {
+        let root = verbatim_root();
+        assert_eq!(Root(&root).emit(), SOURCE);
+        let tokens = root.tokens().unwrap();
+        assert_eq!(tokens.last().unwrap().span.end, SOURCE.len());
+    }
+
+    #[test]
+    fn nested_edit_keeps_untouched_siblings() {
+        let mut root = verbatim_root();
+        let foo = &mut root.expressions_mut()[0];
+        let mut bodies = foo.bodies_mut();
+        let b = &mut bodies[0].0[1];
+        b.bodies_mut()[0].0[0] = var("y");
+        assert!(root.tokens().is_none());
+        assert_eq!(
+            Root(&root).emit(),
+            "class Foo\n  \
+             def a\n    \
+             1 +   2\n  \
+             end\n\n  \
+             # doc\n  \
+             def b\n    \
+             y\n  \
+             end\n\
+             end\n"
+        );
+    }
+
+    #[test]
+    fn edited_statement_keeps_leading_lines() {
+        let mut root = verbatim_root();
+        let foo = &mut root.expressions_mut()[0];
+        let mut bodies = foo.bodies_mut();
+        let b = bodies[0].0[1].make_mut();
+        b.bodies_mut()[0].0.push(var("z"));
+        assert_eq!(
+            Root(&root).emit(),
+            "class Foo\n  \
+             def a\n    \
+             1 +   2\n  \
+             end\n\n  \
+             # doc\n  \
+             def b\n    \
+             x\n    \
+             z\n  \
+             end\n\
+             end\n"
+        );
+    }
+
+    #[test]
+    fn tokens_of_non_ascii_source() {
+        let mut root = ast::Root::new(vec![assign("x", var("y"))]);
+        root.set_source("x = \"é\" # ü\n".to_string());
+        let tokens = root.tokens().unwrap();
+        assert_eq!(tokens.len(), 10);
+        root.set_shebang(None);
+        assert!(root.tokens().is_none());
+    }
+}
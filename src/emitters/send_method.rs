@@ -0,0 +1,108 @@
+use super::blocks::Block;
+use super::expression::{element, primary};
+use super::literals::{array_expression, hash_element, hash_expression};
+use super::Emitter;
+use crate::ast;
+
+pub struct SendMethod<'s>(pub(crate) &'s ast::SendMethodVariants);
+
+impl<'s> Emitter for SendMethod<'s> {
+    fn emit(&self) -> String {
+        match self.0 {
+            ast::SendMethodVariants::Singleton(sm) => format!(
+                "{}{}",
+                super::definitions::MethodName(&sm.name).emit(),
+                parenthesized_arguments(&sm.args)
+            ),
+            ast::SendMethodVariants::WithReceiver(recv, sm) => with_receiver(recv, sm),
+        }
+    }
+}
+
+fn with_receiver(recv: &ast::Expression, sm: &ast::SendMethod) -> String {
+    let recv = primary(recv);
+    let ast::SendMethodArgument(args, block) = &sm.args;
+    let has_block = block.is_some();
+    match (&sm.name, args.as_slice()) {
+        // `a[1] { }` is a syntax error, call with block is written as `a.[](1) { }`.
+        (ast::MethodName::Index, _) if !has_block => format!("{}[{}]", recv, arguments(args)),
+        (ast::MethodName::IndexSetter, [index @ .., value]) if !has_block => {
+            format!("{}[{}] = {}", recv, arguments(index), argument(value))
+        }
+        (ast::MethodName::Setter(attr), [value]) if !has_block => {
+            format!("{}.{} = {}", recv, attr.0, argument(value))
+        }
+        (ast::MethodName::Operator(op), []) if !has_block => match op {
+            ast::OperatorMethod::UnaryPlus => format!("+{}", recv),
+            ast::OperatorMethod::UnaryMinus => format!("-{}", recv),
+            ast::OperatorMethod::Not => format!("!{}", recv),
+            ast::OperatorMethod::Tilde => format!("~{}", recv),
+            _ => format!(
+                "{}.{}()",
+                recv,
+                super::definitions::MethodName(&sm.name).emit()
+            ),
+        },
+        (ast::MethodName::Operator(op), [ast::ArgumentVariants::Expression(exp)])
+            if !has_block && op.is_binary() =>
+        {
+            format!(
+                "{} {} {}",
+                recv,
+                super::definitions::MethodName(&sm.name).emit(),
+                primary(exp)
+            )
+        }
+        _ => format!(
+            "{}.{}{}",
+            recv,
+            super::definitions::MethodName(&sm.name).emit(),
+            parenthesized_arguments(&sm.args)
+        ),
+    }
+}
+
+/// Emit arguments wrapped in parentheses followed by block if any,
+/// parentheses are omitted when there is no argument.
+/// Block pass (`&blk`) is emitted as the last argument.
+pub(crate) fn parenthesized_arguments(args: &ast::SendMethodArgument) -> String {
+    let ast::SendMethodArgument(args, block) = args;
+    let mut args = args.iter().map(argument).collect::<Vec<String>>();
+    let block = match block {
+        Some(ast::BlockArgument::BeginBlock(..)) | None => block
+            .as_ref()
+            .map_or(String::new(), |blk| Block(blk).emit()),
+        Some(blk) => {
+            args.push(Block(blk).emit());
+            String::new()
+        }
+    };
+    format!(
+        "{}{}",
+        if args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", args.join(", "))
+        },
+        block
+    )
+}
+
+fn arguments(args: &[ast::ArgumentVariants]) -> String {
+    args.iter()
+        .map(argument)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn argument(arg: &ast::ArgumentVariants) -> String {
+    match arg {
+        ast::ArgumentVariants::Expression(exp) => element(exp),
+        ast::ArgumentVariants::Splat(aexp) => array_expression(aexp),
+        ast::ArgumentVariants::Keyword(elt) => hash_element(elt),
+        ast::ArgumentVariants::KeywordSplat(hexp) => hash_expression(hexp),
+        ast::ArgumentVariants::AnonymousSplat => "*".to_string(),
+        ast::ArgumentVariants::AnonymousKeywordSplat => "**".to_string(),
+        ast::ArgumentVariants::ForwardArguments => "...".to_string(),
+    }
+}
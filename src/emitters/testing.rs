@@ -0,0 +1,137 @@
+//! Shorthands building syntax trees for emitter tests.
+
+use super::{statement, unmark};
+use crate::ast;
+
+/// Emit expression as a top level statement.
+pub(crate) fn emit(exp: &ast::Expression) -> String {
+    unmark(&statement(exp))
+}
+
+pub(crate) fn var(name: &str) -> ast::Expression {
+    ast::Expression::Access(ast::AccessVariants::LocalVariable(ast::Variable(
+        name.to_string(),
+    )))
+}
+
+pub(crate) fn constant(name: &str) -> ast::Expression {
+    ast::Expression::Access(ast::AccessVariants::Constant(
+        ast::ConstantVariants::Unscoped(ast::Constant(name.to_string())),
+    ))
+}
+
+pub(crate) fn int(value: i64) -> ast::Expression {
+    ast::Expression::Literal(ast::ValueVariants::Integer(ast::IntegerLiteral(value)))
+}
+
+pub(crate) fn symbol(value: &str) -> ast::Expression {
+    ast::Expression::Literal(ast::ValueVariants::Symbol(
+        ast::StringLiteral::Static(value.to_string()),
+        ast::LiteralForm::Default,
+    ))
+}
+
+pub(crate) fn nil() -> ast::Expression {
+    ast::Expression::Literal(ast::ValueVariants::Singleton(ast::SingletonVariants::Nil))
+}
+
+pub(crate) fn args(args: Vec<ast::Expression>) -> ast::SendMethodArgument {
+    ast::SendMethodArgument(
+        args.into_iter()
+            .map(ast::ArgumentVariants::Expression)
+            .collect(),
+        None,
+    )
+}
+
+pub(crate) fn method(name: &str, args: ast::SendMethodArgument) -> ast::SendMethod {
+    ast::SendMethod {
+        name: ast::MethodName::parse(name).unwrap(),
+        args,
+    }
+}
+
+/// Call without receiver, e.g. `foo(1)`.
+pub(crate) fn call(name: &str, arguments: Vec<ast::Expression>) -> ast::Expression {
+    ast::Expression::SendMethod(ast::SendMethodVariants::Singleton(method(
+        name,
+        args(arguments),
+    )))
+}
+
+/// Call with receiver, e.g. `a.foo(1)`.
+pub(crate) fn send(
+    receiver: ast::Expression,
+    name: &str,
+    arguments: Vec<ast::Expression>,
+) -> ast::Expression {
+    ast::Expression::SendMethod(ast::SendMethodVariants::WithReceiver(
+        Box::new(receiver),
+        method(name, args(arguments)),
+    ))
+}
+
+pub(crate) fn assign(name: &str, value: ast::Expression) -> ast::Expression {
+    ast::Expression::Assignment(ast::AssignmentVariants::ToLocalVariable(
+        ast::Variable(name.to_string()),
+        Box::new(value),
+    ))
+}
+
+pub(crate) fn binary(
+    left: ast::Expression,
+    operator: ast::BinaryOperator,
+    right: ast::Expression,
+) -> ast::Expression {
+    ast::Expression::Operation(ast::OperationVariants::BinaryExpression(
+        ast::BinaryExpressionOperation {
+            operator,
+            lefthand: Box::new(left),
+            righthand: Box::new(right),
+        },
+    ))
+}
+
+pub(crate) fn body(expressions: Vec<ast::Expression>) -> ast::Body {
+    ast::Body(expressions)
+}
+
+/// Required parameters, e.g. `(a, b)`.
+pub(crate) fn params(names: &[&str]) -> ast::FormalArgument {
+    ast::FormalArgument(
+        names
+            .iter()
+            .map(|name| ast::Parameter::Required(ast::Variable(name.to_string())))
+            .collect(),
+    )
+}
+
+/// Instance method definition.
+pub(crate) fn def(
+    name: &str,
+    args: ast::FormalArgument,
+    expressions: Vec<ast::Expression>,
+) -> ast::Expression {
+    ast::Expression::MethodDefinition(ast::MethodDefinitionVariants::Instance(
+        ast::InstanceMethod {
+            name: ast::MethodName::parse(name).unwrap(),
+            args,
+            body: body(expressions),
+        },
+    ))
+}
+
+/// `if` without `else`, e.g. `stmt if condition`.
+pub(crate) fn if_(
+    condition: ast::Expression,
+    expressions: Vec<ast::Expression>,
+    form: ast::ModifierForm,
+) -> ast::Expression {
+    ast::Expression::Branching(ast::BranchingVariants::If(
+        ast::BranchingIfVariants::WithoutElse(ast::BranchingIf {
+            condition: Box::new(condition),
+            iftrue: body(expressions),
+            form,
+        }),
+    ))
+}
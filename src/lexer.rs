@@ -0,0 +1,1265 @@
+//! Tokenizer for ruby source code, for tools which don't need the full syntax tree,
+//! e.g. syntax highlighters. Tokens cover the whole source including whitespaces
+//! and comments, so concatenating their spans gives back the source.
+//! Spellings are shared with the syntax tree, e.g. global variables
+//! are recognized by `ast::GlobalVariable::parse` and operators are spelled
+//! by `ast::OperatorMethod::spelling`, so the lexer agrees with the emitters.
+
+use crate::ast;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Token kind along with its byte range in the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Reserved word, see `KEYWORDS`.
+    Keyword,
+
+    /// Local variable or method name, may end with `?` or `!`.
+    Identifier,
+    Constant,
+
+    /// Hash key or keyword argument, `key:`.
+    Label,
+    InstanceVariable,
+    ClassVariable,
+    GlobalVariable,
+    Integer,
+    Float,
+
+    /// Rational number, `3r`.
+    Rational,
+
+    /// Imaginary number, `2i`.
+    Complex,
+
+    /// Character literal, `?a`.
+    Character,
+
+    /// Static symbol, `:sym`, `:+` or `:@ivar`.
+    Symbol,
+
+    /// `"`, `'`, `%q(` or `%Q(`.
+    StringBegin,
+
+    /// `:"` or `%s(`.
+    SymbolBegin,
+
+    /// `/` or `%r{`.
+    RegularExpressionBegin,
+
+    /// `` ` `` or `%x(`.
+    ExecuteStringBegin,
+
+    /// `%w[` or `%W[`.
+    WordsBegin,
+
+    /// `%i[` or `%I[`.
+    SymbolsBegin,
+
+    /// Content of string-like literal, escapes are kept as written.
+    StringContent,
+
+    /// Closing delimiter, including flags of regular expression, e.g. `/i`.
+    StringEnd,
+
+    /// `#{` in interpolated literal, followed by tokens of the expression,
+    /// or `#` of the short form, `#@ivar`, followed only by the variable.
+    InterpolationBegin,
+
+    /// `}` closing interpolation.
+    InterpolationEnd,
+
+    /// `<<~EOS`, `<<-EOS` or `<<EOS`, possibly quoted. Lines of the document
+    /// follow the line which opens it, as content and interpolation tokens.
+    HereDocumentBegin,
+
+    /// Terminator line of here document, including its indentation.
+    HereDocumentEnd,
+
+    /// Line comment, `# text`.
+    Comment,
+
+    /// Embedded document, `=begin` ... `=end`.
+    EmbeddedDocument,
+
+    /// `__END__` line along with the data after it.
+    DataSection,
+    Operator,
+
+    /// `(`, `)`, `[`, `]`, `{`, `}`, `,` and `;`.
+    Punctuation,
+
+    /// Spaces, tabs and escaped newlines.
+    Whitespace,
+    Newline,
+
+    /// Character which doesn't start any token.
+    Unknown,
+}
+
+/// Reserved words, values are spelled by the syntax tree.
+pub const KEYWORDS: [&str; 41] = [
+    "BEGIN",
+    "END",
+    ENCODING,
+    FILE,
+    LINE,
+    "alias",
+    "and",
+    "begin",
+    "break",
+    "case",
+    "class",
+    "def",
+    "defined?",
+    "do",
+    "else",
+    "elsif",
+    "end",
+    "ensure",
+    ast::SingletonVariants::False.keyword(),
+    "for",
+    "if",
+    "in",
+    "module",
+    "next",
+    ast::SingletonVariants::Nil.keyword(),
+    "not",
+    "or",
+    "redo",
+    "rescue",
+    "retry",
+    "return",
+    "self",
+    "super",
+    "then",
+    ast::SingletonVariants::True.keyword(),
+    "undef",
+    "unless",
+    "until",
+    "when",
+    "while",
+    "yield",
+];
+
+/// Keywords which end a value, so the next `/` is division rather than regular expression.
+const VALUE_KEYWORDS: [&str; 10] = [
+    ENCODING,
+    FILE,
+    LINE,
+    "end",
+    ast::SingletonVariants::False.keyword(),
+    ast::SingletonVariants::Nil.keyword(),
+    "redo",
+    "retry",
+    "self",
+    ast::SingletonVariants::True.keyword(),
+];
+
+/// Special constants, spelled by `ast::ConstantVariants::keyword`.
+const ENCODING: &str = ast::ConstantVariants::Encoding.keyword().unwrap();
+const FILE: &str = ast::ConstantVariants::File.keyword().unwrap();
+const LINE: &str = ast::ConstantVariants::Line.keyword().unwrap();
+
+/// Operators which aren't spelled by the syntax tree.
+const PUNCTUATION_OPERATORS: [&str; 11] = [
+    "**=", "...", "..", "::", "->", "=>", "&.", "=", "?", ":", ".",
+];
+
+lazy_static! {
+    /// Operators, longer spellings come first so the longest one is matched.
+    /// Operator methods, binary and logical operators along with their
+    /// assignment forms are spelled by the syntax tree.
+    static ref OPERATORS: Vec<String> = {
+        let methods = IntoIterator::into_iter(ast::OperatorMethod::ALL)
+            // `+@`, `-@` and `` ` `` are only method names.
+            .filter(|op| {
+                !matches!(
+                    op,
+                    ast::OperatorMethod::UnaryPlus
+                        | ast::OperatorMethod::UnaryMinus
+                        | ast::OperatorMethod::Backtick
+                )
+            })
+            .map(|op| op.spelling().to_string());
+        let binary = IntoIterator::into_iter(ast::BinaryOperator::ALL)
+            .map(|op| format!("{}=", op.spelling()));
+        let logical = IntoIterator::into_iter(ast::LogicalOperator::ALL)
+            .flat_map(|op| vec![op.spelling().to_string(), format!("{}=", op.spelling())]);
+        let punctuation = PUNCTUATION_OPERATORS.iter().map(|op| op.to_string());
+        longest_first(methods.chain(binary).chain(logical).chain(punctuation))
+    };
+
+    /// Operator method names written as a symbol, e.g. `:+`, `:[]=` or `` :` ``.
+    static ref SYMBOL_OPERATORS: Vec<String> = {
+        let methods = IntoIterator::into_iter(ast::OperatorMethod::ALL)
+            .map(|op| op.spelling().to_string());
+        let others = ["[]=", "[]", "~@"].iter().map(|op| op.to_string());
+        longest_first(methods.chain(others))
+    };
+}
+
+fn longest_first(spellings: impl Iterator<Item = String>) -> Vec<String> {
+    let mut spellings = spellings.collect::<Vec<String>>();
+    spellings.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    spellings.dedup();
+    spellings
+}
+
+/// Split source into tokens.
+pub fn lex(source: &str) -> impl Iterator<Item = Token> + '_ {
+    Lexer {
+        source,
+        pos: 0,
+        beginning: true,
+        last: None,
+        pending: VecDeque::new(),
+        heredocs: Vec::new(),
+        modes: Vec::new(),
+        brackets: 0,
+        ternaries: Vec::new(),
+    }
+}
+
+struct Lexer<'s> {
+    source: &'s str,
+    pos: usize,
+
+    /// Whether an expression may begin here, e.g. `/` starts a regular expression.
+    beginning: bool,
+
+    /// Last token other than whitespace and comment.
+    last: Option<TokenKind>,
+
+    /// Tokens which are produced together, e.g. here document bodies.
+    pending: VecDeque<Token>,
+
+    /// Here documents whose bodies start on the next line, in order.
+    heredocs: Vec<HereDocument>,
+
+    /// Literals and interpolations being lexed, innermost last.
+    modes: Vec<Mode>,
+
+    /// Depth of open parentheses, brackets and braces.
+    brackets: usize,
+
+    /// Bracket depth of each `?` waiting for its `:`, innermost last.
+    ternaries: Vec<usize>,
+}
+
+struct HereDocument {
+    identifier: String,
+
+    /// Terminator may be indented, `<<~` and `<<-`.
+    indented: bool,
+
+    /// Whether the body is interpolated, i.e. the identifier isn't single quoted.
+    interpolate: bool,
+}
+
+enum Mode {
+    Literal {
+        open: char,
+        close: char,
+        depth: usize,
+        interpolate: bool,
+        regex: bool,
+    },
+
+    /// Inside `#{`, counts nested braces.
+    Interpolation(usize),
+
+    /// Lines of here document up to its terminator.
+    HereDocument(HereDocument),
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if let Some(token) = self.pending.pop_front() {
+            self.pos = self.pos.max(token.span.end);
+            return Some(token);
+        }
+        if self.pos >= self.source.len() {
+            return None;
+        }
+        let start = self.pos;
+        let kind = match self.modes.last() {
+            Some(Mode::Literal { .. }) => self.literal_content(),
+            Some(Mode::HereDocument(_)) => self.heredoc_content(),
+            _ => self.token(),
+        };
+        let end = self.pos;
+        if kind == TokenKind::Newline {
+            self.heredoc_bodies();
+        }
+        match kind {
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::EmbeddedDocument => (),
+            kind => {
+                let text = &self.source[start..end];
+                self.beginning = begins_expression(kind, text);
+                self.last = Some(kind);
+                self.nest_ternary(kind, text);
+            }
+        }
+        Some(Token {
+            kind,
+            span: start..end,
+        })
+    }
+}
+
+/// Whether an expression may begin after the token.
+fn begins_expression(kind: TokenKind, text: &str) -> bool {
+    match kind {
+        TokenKind::Keyword => !VALUE_KEYWORDS.contains(&text),
+        TokenKind::Punctuation => !matches!(text, ")" | "]" | "}"),
+        TokenKind::Operator
+        | TokenKind::Label
+        | TokenKind::Newline
+        | TokenKind::StringBegin
+        | TokenKind::SymbolBegin
+        | TokenKind::RegularExpressionBegin
+        | TokenKind::ExecuteStringBegin
+        | TokenKind::WordsBegin
+        | TokenKind::SymbolsBegin
+        | TokenKind::InterpolationBegin => true,
+        _ => false,
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+/// Instance, class or global variable at the start of `rest`, along with its length.
+/// Global variable is validated by `ast::GlobalVariable::parse`.
+fn variable(rest: &str) -> Option<(TokenKind, usize)> {
+    let identifier = |s: &str| {
+        if !s.starts_with(is_identifier_start) {
+            return None;
+        }
+        Some(s.find(|c| !is_identifier_char(c)).unwrap_or(s.len()))
+    };
+    if let Some(name) = rest.strip_prefix("@@") {
+        return Some((TokenKind::ClassVariable, 2 + identifier(name)?));
+    }
+    if let Some(name) = rest.strip_prefix('@') {
+        return Some((TokenKind::InstanceVariable, 1 + identifier(name)?));
+    }
+    let name = rest.strip_prefix('$')?;
+    let mut chars = name.chars();
+    let len = match chars.next()? {
+        '-' => 1 + chars.next()?.len_utf8(),
+        c if c.is_ascii_digit() && c != '0' => name
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(name.len()),
+        c if is_identifier_start(c) => identifier(name)?,
+        c => c.len_utf8(),
+    };
+    ast::GlobalVariable::parse(&rest[..1 + len])?;
+    Some((TokenKind::GlobalVariable, 1 + len))
+}
+
+/// Closing delimiter of percent literal, bracket delimiters are paired.
+fn closing(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c,
+    }
+}
+
+impl<'s> Lexer<'s> {
+    fn rest(&self) -> &'s str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    /// Track brackets and ternaries, so `:` of `a ? b:c` isn't taken as a label or a symbol.
+    fn nest_ternary(&mut self, kind: TokenKind, text: &str) {
+        match (kind, text) {
+            (TokenKind::Punctuation, "(" | "[" | "{") => self.brackets += 1,
+            (TokenKind::Punctuation, ")" | "]" | "}") => {
+                self.brackets = self.brackets.saturating_sub(1);
+                while self
+                    .ternaries
+                    .last()
+                    .is_some_and(|&depth| depth > self.brackets)
+                {
+                    self.ternaries.pop();
+                }
+            }
+            (TokenKind::Operator, "?") => self.ternaries.push(self.brackets),
+            (TokenKind::Operator, ":") if self.in_ternary() => {
+                self.ternaries.pop();
+            }
+            _ => (),
+        }
+    }
+
+    /// Whether a `?` at the current bracket depth is waiting for its `:`.
+    fn in_ternary(&self) -> bool {
+        self.ternaries.last() == Some(&self.brackets)
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.source[..self.pos].ends_with('\n')
+    }
+
+    /// Whether the previous token is an identifier followed by a space and the character
+    /// after `len` bytes isn't a space, e.g. `puts /x/` or `puts %w[a]`,
+    /// in which case the operator-like character starts an argument.
+    fn spaced_argument(&self, len: usize) -> bool {
+        self.last == Some(TokenKind::Identifier)
+            && self.source[..self.pos].ends_with([' ', '\t'])
+            && self.rest()[len..]
+                .chars()
+                .next()
+                .is_some_and(|c| !c.is_whitespace() && c != '=')
+    }
+
+    fn token(&mut self) -> TokenKind {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return TokenKind::Unknown,
+        };
+        if self.at_line_start() {
+            if let Some(kind) = self.line_start_token() {
+                return kind;
+            }
+        }
+        match c {
+            '\n' => {
+                self.bump();
+                TokenKind::Newline
+            }
+            '\r' if self.peek_at(1) == Some('\n') => {
+                self.pos += 2;
+                TokenKind::Newline
+            }
+            ' ' | '\t' | '\r' => {
+                self.eat_while(|c| matches!(c, ' ' | '\t' | '\r'));
+                TokenKind::Whitespace
+            }
+            '\\' if self.peek_at(1) == Some('\n') => {
+                self.pos += 2;
+                TokenKind::Whitespace
+            }
+            '#' => {
+                self.eat_while(|c| c != '\n');
+                TokenKind::Comment
+            }
+            '0'..='9' => self.number(),
+            '@' | '$' => match variable(self.rest()) {
+                Some((kind, len)) => {
+                    self.pos += len;
+                    kind
+                }
+                None => {
+                    self.bump();
+                    TokenKind::Unknown
+                }
+            },
+            '"' | '\'' | '`' => {
+                self.bump();
+                self.modes.push(Mode::Literal {
+                    open: c,
+                    close: c,
+                    depth: 0,
+                    interpolate: c != '\'',
+                    regex: false,
+                });
+                if c == '`' {
+                    TokenKind::ExecuteStringBegin
+                } else {
+                    TokenKind::StringBegin
+                }
+            }
+            ':' => self.symbol(),
+            '?' if self.beginning || self.spaced_argument(1) => self.character(),
+            '/' if self.beginning || self.spaced_argument(1) => {
+                self.bump();
+                self.modes.push(Mode::Literal {
+                    open: '/',
+                    close: '/',
+                    depth: 0,
+                    interpolate: true,
+                    regex: true,
+                });
+                TokenKind::RegularExpressionBegin
+            }
+            '%' if self.beginning || self.spaced_argument(1) => self.percent_literal(),
+            '<' if self.rest().starts_with("<<") && (self.beginning || self.spaced_argument(2)) => {
+                match self.heredoc_begin() {
+                    Some(kind) => kind,
+                    None => self.operator(),
+                }
+            }
+            '{' => {
+                if let Some(Mode::Interpolation(depth)) = self.modes.last_mut() {
+                    *depth += 1;
+                }
+                self.bump();
+                TokenKind::Punctuation
+            }
+            '}' => {
+                self.bump();
+                match self.modes.last_mut() {
+                    Some(Mode::Interpolation(0)) => {
+                        self.modes.pop();
+                        TokenKind::InterpolationEnd
+                    }
+                    Some(Mode::Interpolation(depth)) => {
+                        *depth -= 1;
+                        TokenKind::Punctuation
+                    }
+                    _ => TokenKind::Punctuation,
+                }
+            }
+            '(' | ')' | '[' | ']' | ',' | ';' => {
+                self.bump();
+                TokenKind::Punctuation
+            }
+            c if is_identifier_start(c) => self.identifier(),
+            _ => self.operator(),
+        }
+    }
+
+    /// Embedded document and data section, which are only recognized at line start.
+    fn line_start_token(&mut self) -> Option<TokenKind> {
+        let rest = self.rest();
+        let line = rest.split('\n').next().unwrap_or_default();
+        if line.trim_end_matches('\r') == "__END__" {
+            self.pos = self.source.len();
+            return Some(TokenKind::DataSection);
+        }
+        if !starts_with_word(rest, "=begin") {
+            return None;
+        }
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if starts_with_word(line, "=end") {
+                // Newline after `=end` line is a token on its own.
+                offset += line.trim_end_matches(['\r', '\n']).len();
+                break;
+            }
+            offset += line.len();
+        }
+        self.pos += offset;
+        Some(TokenKind::EmbeddedDocument)
+    }
+
+    fn operator(&mut self) -> TokenKind {
+        let rest = self.rest();
+        match OPERATORS.iter().find(|op| rest.starts_with(op.as_str())) {
+            Some(op) => {
+                self.pos += op.len();
+                TokenKind::Operator
+            }
+            None => {
+                self.bump();
+                TokenKind::Unknown
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> TokenKind {
+        let start = self.pos;
+        self.eat_while(is_identifier_char);
+        // `?` and `!` are part of method name unless they start `!=` or `?=`.
+        if matches!(self.peek(), Some('?' | '!')) && self.peek_at(1) != Some('=') {
+            self.bump();
+        }
+        let text = &self.source[start..self.pos];
+        let method_call = self.last == Some(TokenKind::Operator)
+            && matches!(self.source[..start].trim_end().chars().last(), Some('.'));
+        let label = self.peek() == Some(':') && self.peek_at(1) != Some(':');
+        if label && !method_call && !self.in_ternary() {
+            self.bump();
+            return TokenKind::Label;
+        }
+        if KEYWORDS.contains(&text) && !method_call {
+            return TokenKind::Keyword;
+        }
+        if text.starts_with(char::is_uppercase) {
+            TokenKind::Constant
+        } else {
+            TokenKind::Identifier
+        }
+    }
+
+    fn number(&mut self) -> TokenKind {
+        let mut kind = TokenKind::Integer;
+        let radix = match (self.peek(), self.peek_at(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('b' | 'B')) => 2,
+            (Some('0'), Some('o' | 'O' | '_')) => 8,
+            (Some('0'), Some(c)) if c.is_ascii_digit() => 8,
+            _ => 10,
+        };
+        let prefixed = matches!(
+            (self.peek(), self.peek_at(1)),
+            (
+                Some('0'),
+                Some('x' | 'X' | 'b' | 'B' | 'o' | 'O' | 'd' | 'D')
+            )
+        );
+        if prefixed {
+            self.pos += 2;
+        }
+        self.eat_while(|c| c.is_digit(radix) || c == '_');
+        if radix == 10 {
+            if self.peek() == Some('.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+                kind = TokenKind::Float;
+                self.bump();
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            }
+            let exponent = matches!(self.peek(), Some('e' | 'E'))
+                && match self.peek_at(1) {
+                    Some('+' | '-') => self.peek_at(2).is_some_and(|c| c.is_ascii_digit()),
+                    Some(c) => c.is_ascii_digit(),
+                    None => false,
+                };
+            if exponent {
+                kind = TokenKind::Float;
+                self.pos += 2;
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            }
+        }
+        let suffix_end = |c: Option<char>| c.is_none_or(|c| !is_identifier_char(c));
+        if self.peek() == Some('r') && suffix_end(self.peek_at(1)) {
+            self.bump();
+            kind = TokenKind::Rational;
+        } else if self.rest().starts_with("ri") && suffix_end(self.peek_at(2)) {
+            self.pos += 2;
+            kind = TokenKind::Complex;
+        } else if self.peek() == Some('i') && suffix_end(self.peek_at(1)) {
+            self.bump();
+            kind = TokenKind::Complex;
+        }
+        kind
+    }
+
+    /// Symbol or opening of quoted symbol, otherwise `::` and `:` are operators.
+    fn symbol(&mut self) -> TokenKind {
+        let rest = self.rest();
+        if rest.starts_with("::") {
+            self.pos += 2;
+            return TokenKind::Operator;
+        }
+        // `:` after the value of a ternary branch, e.g. `a ? 1 :b`.
+        if self.in_ternary() && !self.beginning {
+            self.bump();
+            return TokenKind::Operator;
+        }
+        match self.peek_at(1) {
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 2;
+                self.modes.push(Mode::Literal {
+                    open: quote,
+                    close: quote,
+                    depth: 0,
+                    interpolate: quote == '"',
+                    regex: false,
+                });
+                return TokenKind::SymbolBegin;
+            }
+            Some(c) if is_identifier_start(c) => {
+                self.bump();
+                self.eat_while(is_identifier_char);
+                // Predicate, bang and setter method names, but not `:a==b`.
+                if matches!(self.peek(), Some('?' | '!' | '='))
+                    && !matches!(self.peek_at(1), Some('=' | '~' | '>'))
+                {
+                    self.bump();
+                }
+                return TokenKind::Symbol;
+            }
+            Some('@' | '$') => {
+                if let Some((_, len)) = variable(&rest[1..]) {
+                    self.pos += 1 + len;
+                    return TokenKind::Symbol;
+                }
+            }
+            _ => (),
+        }
+        let operator = SYMBOL_OPERATORS
+            .iter()
+            .find(|op| rest[1..].starts_with(op.as_str()));
+        match operator {
+            Some(op) => {
+                self.pos += 1 + op.len();
+                TokenKind::Symbol
+            }
+            None => {
+                self.bump();
+                TokenKind::Operator
+            }
+        }
+    }
+
+    /// Character literal, `?a`, `?\n` or `?\M-\C-x`, otherwise `?` is an operator.
+    fn character(&mut self) -> TokenKind {
+        match self.peek_at(1) {
+            Some('\\') => {
+                self.pos += 2;
+                self.escape_sequence();
+                TokenKind::Character
+            }
+            Some(c) if !c.is_whitespace() && !self.peek_at(2).is_some_and(is_identifier_char) => {
+                self.pos += 1 + c.len_utf8();
+                TokenKind::Character
+            }
+            _ => {
+                self.bump();
+                TokenKind::Operator
+            }
+        }
+    }
+
+    /// Skip escape sequence after backslash, e.g. `n`, `x7F`, `u{41}` or `M-\C-x`.
+    fn escape_sequence(&mut self) {
+        let digits = |lexer: &mut Lexer, max: usize, radix: u32| {
+            for _ in 0..max {
+                if !lexer.peek().is_some_and(|c| c.is_digit(radix)) {
+                    break;
+                }
+                lexer.bump();
+            }
+        };
+        match self.bump() {
+            Some('M' | 'C') if self.peek() == Some('-') => {
+                self.bump();
+                self.meta_or_control();
+            }
+            Some('c') => self.meta_or_control(),
+            Some('u') if self.peek() == Some('{') => {
+                self.eat_while(|c| c != '}' && c != '\n');
+                self.bump();
+            }
+            Some('u') => digits(self, 4, 16),
+            Some('x') => digits(self, 2, 16),
+            Some('0'..='7') => digits(self, 2, 8),
+            _ => (),
+        }
+    }
+
+    fn meta_or_control(&mut self) {
+        if self.bump() == Some('\\') {
+            self.escape_sequence();
+        }
+    }
+
+    /// Percent literal, e.g. `%w[a b]`, `%r{x}i` or `%(str)`.
+    fn percent_literal(&mut self) -> TokenKind {
+        let (kind, interpolate, regex, len) = match self.peek_at(1) {
+            Some('q') => (TokenKind::StringBegin, false, false, 2),
+            Some('Q') => (TokenKind::StringBegin, true, false, 2),
+            Some('w') => (TokenKind::WordsBegin, false, false, 2),
+            Some('W') => (TokenKind::WordsBegin, true, false, 2),
+            Some('i') => (TokenKind::SymbolsBegin, false, false, 2),
+            Some('I') => (TokenKind::SymbolsBegin, true, false, 2),
+            Some('s') => (TokenKind::SymbolBegin, false, false, 2),
+            Some('r') => (TokenKind::RegularExpressionBegin, true, true, 2),
+            Some('x') => (TokenKind::ExecuteStringBegin, true, false, 2),
+            _ => (TokenKind::StringBegin, true, false, 1),
+        };
+        match self.rest()[len..].chars().next() {
+            Some(open) if !open.is_alphanumeric() && !open.is_whitespace() => {
+                self.pos += len + open.len_utf8();
+                self.modes.push(Mode::Literal {
+                    open,
+                    close: closing(open),
+                    depth: 0,
+                    interpolate,
+                    regex,
+                });
+                kind
+            }
+            _ => self.operator(),
+        }
+    }
+
+    /// Content, interpolation or closing delimiter of the innermost literal.
+    fn literal_content(&mut self) -> TokenKind {
+        let (open, close, interpolate, regex) = match self.modes.last() {
+            Some(Mode::Literal {
+                open,
+                close,
+                interpolate,
+                regex,
+                ..
+            }) => (*open, *close, *interpolate, *regex),
+            _ => return TokenKind::Unknown,
+        };
+        let rest = self.rest();
+        if rest.starts_with(close) && self.literal_depth() == 0 {
+            self.bump();
+            if regex {
+                self.eat_while(|c| "eimnosux".contains(c));
+            }
+            self.modes.pop();
+            return TokenKind::StringEnd;
+        }
+        if interpolate {
+            if let Some(kind) = self.interpolation_begin() {
+                return kind;
+            }
+        }
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.bump();
+                self.bump();
+                continue;
+            }
+            if c == close && self.literal_depth() == 0 {
+                break;
+            }
+            if interpolate && self.at_interpolation() {
+                break;
+            }
+            if open != close && c == open {
+                self.nest(1);
+            } else if open != close && c == close {
+                self.nest(-1);
+            }
+            self.bump();
+        }
+        TokenKind::StringContent
+    }
+
+    /// Whether `#{` or the short form, e.g. `#@ivar`, starts here.
+    fn at_interpolation(&self) -> bool {
+        match self.rest().strip_prefix('#') {
+            Some(rest) => rest.starts_with('{') || variable(rest).is_some(),
+            None => false,
+        }
+    }
+
+    /// `#{` followed by tokens of the expression, or `#` of the short form
+    /// followed by the variable, returns `None` if no interpolation starts here.
+    fn interpolation_begin(&mut self) -> Option<TokenKind> {
+        let rest = self.rest().strip_prefix('#')?;
+        if rest.starts_with('{') {
+            self.pos += 2;
+            self.modes.push(Mode::Interpolation(0));
+            return Some(TokenKind::InterpolationBegin);
+        }
+        let (kind, len) = variable(rest)?;
+        self.pending.push_back(Token {
+            kind,
+            span: self.pos + 1..self.pos + 1 + len,
+        });
+        self.bump();
+        Some(TokenKind::InterpolationBegin)
+    }
+
+    fn literal_depth(&self) -> usize {
+        match self.modes.last() {
+            Some(Mode::Literal { depth, .. }) => *depth,
+            _ => 0,
+        }
+    }
+
+    /// Track nested bracket delimiters, e.g. `%w(a (b))`.
+    fn nest(&mut self, delta: isize) {
+        if let Some(Mode::Literal { depth, .. }) = self.modes.last_mut() {
+            *depth = depth.saturating_add_signed(delta);
+        }
+    }
+
+    /// Here document opening, `<<~EOS`, `<<-"EOS"` or `<<'EOS'`,
+    /// returns `None` if it's a left shift.
+    fn heredoc_begin(&mut self) -> Option<TokenKind> {
+        let rest = &self.rest()[2..];
+        let (indented, flag) = match rest.chars().next()? {
+            '~' | '-' => (true, 1),
+            _ => (false, 0),
+        };
+        let rest = &rest[flag..];
+        let quote = rest.chars().next()?;
+        let (identifier, len) = match quote {
+            quote @ ('"' | '\'' | '`') => {
+                let end = rest[1..].find(quote)?;
+                let identifier = &rest[1..1 + end];
+                if identifier.contains('\n') {
+                    return None;
+                }
+                (identifier, end + 2)
+            }
+            c if is_identifier_char(c) => {
+                let end = rest.find(|c| !is_identifier_char(c)).unwrap_or(rest.len());
+                (&rest[..end], end)
+            }
+            _ => return None,
+        };
+        self.heredocs.push(HereDocument {
+            identifier: identifier.to_string(),
+            indented,
+            interpolate: quote != '\'',
+        });
+        self.pos += 2 + flag + len;
+        Some(TokenKind::HereDocumentBegin)
+    }
+
+    /// Start bodies of here documents opened on the line just ended,
+    /// each one follows the terminator of the previous one.
+    fn heredoc_bodies(&mut self) {
+        for heredoc in std::mem::take(&mut self.heredocs).into_iter().rev() {
+            self.modes.push(Mode::HereDocument(heredoc));
+        }
+    }
+
+    /// Content, interpolation or terminator of the innermost here document.
+    /// Content covers whole lines up to an interpolation or the terminator line.
+    fn heredoc_content(&mut self) -> TokenKind {
+        let (identifier, indented, interpolate) = match self.modes.last() {
+            Some(Mode::HereDocument(heredoc)) => (
+                heredoc.identifier.clone(),
+                heredoc.indented,
+                heredoc.interpolate,
+            ),
+            _ => return TokenKind::Unknown,
+        };
+        // Newline after the terminator of the previous document on the same line.
+        if self.last == Some(TokenKind::HereDocumentEnd) && !self.at_line_start() {
+            if let Some(newline) = ["\r\n", "\n"]
+                .iter()
+                .find(|nl| self.rest().starts_with(**nl))
+            {
+                self.pos += newline.len();
+                return TokenKind::Newline;
+            }
+        }
+        if interpolate {
+            if let Some(kind) = self.interpolation_begin() {
+                return kind;
+            }
+        }
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if self.at_line_start() {
+                let line = self.rest().split('\n').next().unwrap_or_default();
+                let text = line.trim_end_matches('\r');
+                let name = if indented { text.trim_start() } else { text };
+                if name == identifier {
+                    if self.pos > start {
+                        break;
+                    }
+                    self.pos += text.len();
+                    self.modes.pop();
+                    return TokenKind::HereDocumentEnd;
+                }
+            }
+            if interpolate && c == '\\' {
+                self.bump();
+                self.bump();
+                continue;
+            }
+            if interpolate && self.at_interpolation() {
+                break;
+            }
+            self.bump();
+        }
+        TokenKind::StringContent
+    }
+}
+
+/// Whether line starts with the word, e.g. `=begin` but not `=beginning`.
+fn starts_with_word(line: &str, word: &str) -> bool {
+    line.strip_prefix(word)
+        .is_some_and(|rest| rest.chars().next().is_none_or(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokens other than whitespaces along with their text,
+    /// checking that spans cover the whole source.
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        let tokens = lex(source).collect::<Vec<Token>>();
+        let text = tokens
+            .iter()
+            .map(|t| &source[t.span.clone()])
+            .collect::<String>();
+        assert_eq!(text, source);
+        tokens
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, &source[t.span]))
+            .collect()
+    }
+
+    #[test]
+    fn heredoc() {
+        assert_eq!(
+            tokens("f(<<~EOS, 1)\n  a #{b}\n  EOS\nc\n"),
+            vec![
+                (TokenKind::Identifier, "f"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::HereDocumentBegin, "<<~EOS"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::StringContent, "  a "),
+                (TokenKind::InterpolationBegin, "#{"),
+                (TokenKind::Identifier, "b"),
+                (TokenKind::InterpolationEnd, "}"),
+                (TokenKind::StringContent, "\n"),
+                (TokenKind::HereDocumentEnd, "  EOS"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::Identifier, "c"),
+                (TokenKind::Newline, "\n"),
+            ]
+        );
+        assert_eq!(
+            tokens("<<'A' + <<B\n#{a}\nA\n#@b c\n B\nB\n"),
+            vec![
+                (TokenKind::HereDocumentBegin, "<<'A'"),
+                (TokenKind::Operator, "+"),
+                (TokenKind::HereDocumentBegin, "<<B"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::StringContent, "#{a}\n"),
+                (TokenKind::HereDocumentEnd, "A"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::InterpolationBegin, "#"),
+                (TokenKind::InstanceVariable, "@b"),
+                (TokenKind::StringContent, " c\n B\n"),
+                (TokenKind::HereDocumentEnd, "B"),
+                (TokenKind::Newline, "\n"),
+            ]
+        );
+        assert_eq!(
+            tokens("x = <<~EOS\n  é\\\n  EOS\n"),
+            vec![
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Operator, "="),
+                (TokenKind::HereDocumentBegin, "<<~EOS"),
+                (TokenKind::Newline, "\n"),
+                (TokenKind::StringContent, "  é\\\n"),
+                (TokenKind::HereDocumentEnd, "  EOS"),
+                (TokenKind::Newline, "\n"),
+            ]
+        );
+        assert_eq!(
+            tokens("a << b"),
+            vec![
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "<<"),
+                (TokenKind::Identifier, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_literal() {
+        assert_eq!(
+            tokens("%w[a (b)] + %r{x}i"),
+            vec![
+                (TokenKind::WordsBegin, "%w["),
+                (TokenKind::StringContent, "a (b)"),
+                (TokenKind::StringEnd, "]"),
+                (TokenKind::Operator, "+"),
+                (TokenKind::RegularExpressionBegin, "%r{"),
+                (TokenKind::StringContent, "x"),
+                (TokenKind::StringEnd, "}i"),
+            ]
+        );
+        assert_eq!(
+            tokens("a % b"),
+            vec![
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "%"),
+                (TokenKind::Identifier, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn character() {
+        assert_eq!(
+            tokens("[?a, ?\\n, ?\\C-x]"),
+            vec![
+                (TokenKind::Punctuation, "["),
+                (TokenKind::Character, "?a"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Character, "?\\n"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Character, "?\\C-x"),
+                (TokenKind::Punctuation, "]"),
+            ]
+        );
+        assert_eq!(
+            tokens("a.empty? ? 1 : 2"),
+            vec![
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "."),
+                (TokenKind::Identifier, "empty?"),
+                (TokenKind::Operator, "?"),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Operator, ":"),
+                (TokenKind::Integer, "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn label_and_ternary() {
+        assert_eq!(
+            tokens("f(k: 1)"),
+            vec![
+                (TokenKind::Identifier, "f"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::Label, "k:"),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Punctuation, ")"),
+            ]
+        );
+        assert_eq!(
+            tokens("a ? b:c"),
+            vec![
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "?"),
+                (TokenKind::Identifier, "b"),
+                (TokenKind::Operator, ":"),
+                (TokenKind::Identifier, "c"),
+            ]
+        );
+        assert_eq!(
+            tokens("a ? {k: 1} :sym"),
+            vec![
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "?"),
+                (TokenKind::Punctuation, "{"),
+                (TokenKind::Label, "k:"),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Punctuation, "}"),
+                (TokenKind::Operator, ":"),
+                (TokenKind::Identifier, "sym"),
+            ]
+        );
+    }
+
+    #[test]
+    fn regular_expression_and_division() {
+        assert_eq!(
+            tokens("x = /a/ if 4/2"),
+            vec![
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Operator, "="),
+                (TokenKind::RegularExpressionBegin, "/"),
+                (TokenKind::StringContent, "a"),
+                (TokenKind::StringEnd, "/"),
+                (TokenKind::Keyword, "if"),
+                (TokenKind::Integer, "4"),
+                (TokenKind::Operator, "/"),
+                (TokenKind::Integer, "2"),
+            ]
+        );
+        assert_eq!(
+            tokens("puts /a/; a / b"),
+            vec![
+                (TokenKind::Identifier, "puts"),
+                (TokenKind::RegularExpressionBegin, "/"),
+                (TokenKind::StringContent, "a"),
+                (TokenKind::StringEnd, "/"),
+                (TokenKind::Punctuation, ";"),
+                (TokenKind::Identifier, "a"),
+                (TokenKind::Operator, "/"),
+                (TokenKind::Identifier, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn operator_symbol() {
+        assert_eq!(
+            tokens("[:`, :[]=, :<=>, :-@]"),
+            vec![
+                (TokenKind::Punctuation, "["),
+                (TokenKind::Symbol, ":`"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Symbol, ":[]="),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Symbol, ":<=>"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::Symbol, ":-@"),
+                (TokenKind::Punctuation, "]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_ascii() {
+        assert_eq!(
+            tokens("x = \"é#{y}ü\""),
+            vec![
+                (TokenKind::Identifier, "x"),
+                (TokenKind::Operator, "="),
+                (TokenKind::StringBegin, "\""),
+                (TokenKind::StringContent, "é"),
+                (TokenKind::InterpolationBegin, "#{"),
+                (TokenKind::Identifier, "y"),
+                (TokenKind::InterpolationEnd, "}"),
+                (TokenKind::StringContent, "ü"),
+                (TokenKind::StringEnd, "\""),
+            ]
+        );
+        assert_eq!(
+            tokens(":\"日本\" + %w[é ü]"),
+            vec![
+                (TokenKind::SymbolBegin, ":\""),
+                (TokenKind::StringContent, "日本"),
+                (TokenKind::StringEnd, "\""),
+                (TokenKind::Operator, "+"),
+                (TokenKind::WordsBegin, "%w["),
+                (TokenKind::StringContent, "é ü"),
+                (TokenKind::StringEnd, "]"),
+            ]
+        );
+        assert_eq!(
+            tokens("café = ?é"),
+            vec![
+                (TokenKind::Identifier, "café"),
+                (TokenKind::Operator, "="),
+                (TokenKind::Character, "?é"),
+            ]
+        );
+    }
+}
@@ -3,3 +3,4 @@ extern crate lazy_static;
 
 pub mod ast;
 pub mod emitters;
+pub mod lexer;